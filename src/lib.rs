@@ -0,0 +1,6107 @@
+//! Password Hash Generator
+//!
+//! A cross-platform password hash generation tool supporting multiple hash algorithms and custom formatting.
+//! Supports Windows, Linux, macOS on x86_64 and AArch64 architectures.
+//!
+//! # Features
+//! - Multi-platform support (Windows, Linux, macOS)
+//! - Multiple hash algorithms (MD5, SHA1, SHA256, SHA512, SHA3-256, SHA3-512, SHAKE128, SHAKE256, BLAKE3, Argon2id)
+//! - Flexible configuration system (CLI args, environment variables, config files)
+//! - Custom hash output formatting (truncation, end characters, case conversion)
+//! - Result saving and file operations
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(not(feature = "verify-only"))]
+use std::time::Instant;
+use clap::Parser;
+use regex::Regex;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512, Digest};
+use sha3::{Sha3_256, Sha3_512, Shake128, Shake256};
+use sha3::digest::ExtendableOutput;
+use anyhow::Result;
+use log::debug;
+use serde::Deserialize;
+use chrono::Utc;
+
+/// Platform information
+#[derive(Debug)]
+pub struct PlatformInfo {
+    pub os: String,
+    pub arch: String,
+    pub family: String,
+}
+
+impl PlatformInfo {
+    pub fn new() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            family: std::env::consts::FAMILY.to_string(),
+        }
+    }
+    
+    pub fn display(&self) -> String {
+        format!("{}-{}", self.os, self.arch)
+    }
+}
+
+impl Default for PlatformInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Command line arguments
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Cross-platform Password Hash Generator",
+    long_about = r#"Password Hash Generator
+
+A cross-platform password hash generation tool supporting multiple hash algorithms
+and custom formatting. Supports Windows, Linux, macOS on x86_64 and AArch64 architectures.
+
+Features:
+• Multi-platform support (Windows, Linux, macOS)
+• Multiple hash algorithms (MD5, SHA1, SHA256, SHA512, SHA3-256, SHA3-512, SHAKE128, SHAKE256, BLAKE3, Argon2id)
+• Flexible configuration system (CLI args, environment variables, config files)
+• Custom hash output formatting (truncation, end characters, case conversion)
+• Result saving and file operations
+
+Examples:
+  # Basic usage
+  pass-craft --text "name:john,site:example.com" --hash "method:sha256,cut:10"
+
+  # Same parameters as individual flags
+  pass-craft --text "name:john,site:example.com" --method sha256 --cut 10
+
+  # Read configuration from file
+  pass-craft --file config.txt --save passwords.txt
+
+  # Generate a password for every line of a file
+  pass-craft batch --file config.txt --line-range 2:5 --save passwords.txt
+
+  # Convert a head;tail config file to TOML
+  pass-craft migrate --from config.txt --to config.toml
+
+  # Show platform information  
+  pass-craft --show-platform
+
+  # Show configuration
+  pass-craft --show-config --text "name:test,site:example.com"
+"#,
+    after_help = "See https://github.com/ymc-github/pass-craft for more information."
+)]
+pub struct CliArgs {
+    /// Command
+    #[arg(default_value = "add")]
+    pub cmd: String,
+
+    /// Command argument (check-clock <path>, verify-file <path>, prune-archived <path>)
+    pub target: Option<String>,
+
+    /// Text parameter
+    #[arg(long)]
+    pub text: Option<String>,
+
+    /// Hash parameters
+    #[arg(long)]
+    pub hash: Option<String>,
+
+    /// String key-value configuration
+    #[arg(long)]
+    pub slkv: Option<String>,
+
+    /// Secure string format configuration
+    #[arg(long)]
+    pub sslf: Option<String>,
+
+    /// Save file path (repeatable)
+    #[arg(long)]
+    pub save: Vec<String>,
+
+    /// Input file path, `-` reads the config from stdin
+    #[arg(long)]
+    pub file: Option<String>,
+
+    /// Show configuration and exit
+    #[arg(long, default_value = "false")]
+    pub show_config: bool,
+
+    /// Show platform information and exit
+    #[arg(long, default_value = "false")]
+    pub show_platform: bool,
+
+    /// Fold the number of saved entries for name,site into the base text
+    #[arg(long, default_value = "false")]
+    pub auto_counter: bool,
+
+    /// Per-site hash rules file (site;method:value,cut:value,...)
+    #[arg(long)]
+    pub site_rules: Option<String>,
+
+    /// Also print a verifier hash of the password (algorithm[:salt])
+    #[arg(long)]
+    pub emit_verifier: Option<String>,
+
+    /// Only use lines start:end (1-based, inclusive) of the input file
+    #[arg(long)]
+    pub line_range: Option<String>,
+
+    /// Source file for the migrate command
+    #[arg(long)]
+    pub from: Option<String>,
+
+    /// Target file for the migrate command
+    #[arg(long)]
+    pub to: Option<String>,
+
+    /// Reject the password if it appears in HaveIBeenPwned breach data
+    #[arg(long, default_value = "false")]
+    pub check_hibp: bool,
+
+    /// Apply cut length to digest bytes or to output characters [bytes|chars]
+    #[arg(long)]
+    pub truncate_unit: Option<String>,
+
+    /// Default name and email from git config user.name/user.email
+    #[arg(long, default_value = "false")]
+    pub from_git: bool,
+
+    /// Print the raw untruncated digest of the base text and exit
+    #[arg(long, default_value = "false")]
+    pub print_digest: bool,
+
+    /// Print a fingerprint of the resolved configuration and exit
+    #[arg(long, default_value = "false")]
+    pub config_hash: bool,
+
+    /// Fixed text placed before the final password
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    /// Fixed text placed after the final password
+    #[arg(long)]
+    pub suffix: Option<String>,
+
+    /// Regex the final password (including prefix/suffix) must match
+    #[arg(long)]
+    pub policy_regex: Option<String>,
+
+    /// Allow the emoji output encoding (many sites reject emoji)
+    #[arg(long, default_value = "false")]
+    pub allow_emoji: bool,
+
+    /// Hash algorithm (overrides method in --hash)
+    #[arg(long)]
+    pub method: Option<String>,
+
+    /// Cut length (overrides cut in --hash)
+    #[arg(long)]
+    pub cut: Option<usize>,
+
+    /// End character (overrides end in --hash), only the first character is used
+    #[arg(long)]
+    pub end: Option<String>,
+
+    /// Uppercase start position (overrides upper-start in --hash)
+    #[arg(long)]
+    pub upper_start: Option<usize>,
+
+    /// Salt mixed into the hash input (overrides salt in --hash)
+    #[arg(long)]
+    pub salt: Option<String>,
+
+    /// Show what every algorithm produces for the current input and exit
+    #[arg(long, default_value = "false")]
+    pub compare_algorithms: bool,
+
+    /// Include weak algorithms (MD5, SHA1) in algorithm listings
+    #[arg(long, default_value = "false")]
+    pub allow_weak: bool,
+
+    /// Fail on invalid UTF-8 in input files instead of replacing it
+    #[arg(long, default_value = "false")]
+    pub strict_utf8: bool,
+
+    /// Identity fields hashed, in canonical order (default name,email,site)
+    #[arg(long)]
+    pub fields: Option<String>,
+
+    /// Hash each identity field on its own before combining, as field:method pairs
+    /// (e.g. name:sha256,site:sha1), unassigned fields use --method
+    #[arg(long)]
+    pub field_methods: Option<String>,
+
+    /// Print how long each phase took
+    #[arg(long, default_value = "false")]
+    pub timings: bool,
+
+    /// Print machine-readable JSON where supported
+    #[arg(long, default_value = "false")]
+    pub json_output: bool,
+
+    /// Fold the currently saved password for name,site into the base text
+    #[arg(long, default_value = "false")]
+    pub chain_previous: bool,
+
+    /// Treat configuration warnings as errors
+    #[arg(long, default_value = "false")]
+    pub strict: bool,
+
+    /// Write a starter config file to the platform config directory and exit
+    #[arg(long, default_value = "false")]
+    pub init_config: bool,
+
+    /// Maximum batch entries processed per second (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    pub rate_limit: f64,
+
+    /// Number of password variants to generate
+    #[arg(long, default_value = "1")]
+    pub count: usize,
+
+    /// Counter value of the first variant (default 1)
+    #[arg(long)]
+    pub count_from: Option<usize>,
+
+    /// Refuse to save through a symlink; by default writes follow the link to the real file
+    #[arg(long, default_value = "false")]
+    pub no_follow_symlinks: bool,
+
+    /// Drop repeated components from the base text before hashing (changes output)
+    #[arg(long, default_value = "false")]
+    pub dedup_fields: bool,
+
+    /// Debug: skip every formatting transform and output the raw hex digest (still saved)
+    #[arg(long, default_value = "false")]
+    pub no_transforms: bool,
+
+    /// Batch results buffered before each save file write (1 = write every entry)
+    #[arg(long, default_value = "1")]
+    pub flush_every: usize,
+
+    /// Skip saving when the save file already has an entry for name,site
+    #[arg(long, default_value = "false")]
+    pub only_if_absent: bool,
+
+    /// Fold a fresh random nonce into the base text, the password is not reproducible without it
+    #[arg(long, default_value = "false")]
+    pub random_nonce: bool,
+
+    /// Recompute a --random-nonce password from its printed nonce
+    #[arg(long)]
+    pub nonce: Option<String>,
+
+    /// Explain which fields differ between the --a and --b head;tail configs
+    #[arg(long, default_value = "false")]
+    pub explain_diff: bool,
+
+    /// First head;tail config for --explain-diff
+    #[arg(long)]
+    pub a: Option<String>,
+
+    /// Second head;tail config for --explain-diff
+    #[arg(long)]
+    pub b: Option<String>,
+
+    /// Print the digest of a file, streamed in chunks (uses --method, default SHA512)
+    #[arg(long)]
+    pub hash_file: Option<String>,
+
+    /// Output transforms in order (default: truncate,endchar,case)
+    #[arg(long)]
+    pub pipeline: Option<String>,
+
+    /// Strip a URL scheme, www. prefix and trailing slash from the site before hashing
+    #[arg(long, default_value = "false")]
+    pub canonicalize_site: bool,
+
+    /// Lowercase the site's domain before hashing, a URL path keeps its case
+    #[arg(long, default_value = "false")]
+    pub normalize_site_case: bool,
+
+    /// Print only `export VARNAME='<password>'` for eval in a shell
+    #[arg(long)]
+    pub as_env: Option<String>,
+
+    /// Print the algorithm registry as JSON and exit
+    #[arg(long, default_value = "false")]
+    pub dump_registry: bool,
+
+    /// List the supported algorithms by name and exit
+    #[arg(long, default_value = "false")]
+    pub list_algorithms: bool,
+
+    /// Length-prefix each base-text component so separators inside fields can't collide (changes output)
+    #[arg(long, default_value = "false")]
+    pub unambiguous_fields: bool,
+
+    /// Keep at most N saved entries, older ones move to <save>.archive
+    #[arg(long)]
+    pub max_entries: Option<usize>,
+
+    /// Record the save time as entry metadata (`<!-- name,password,site;time:... -->`)
+    #[arg(long, default_value = "false")]
+    pub save_time: bool,
+
+    /// Generate the password N times and fail if any run differs
+    #[arg(long)]
+    pub verify_determinism: Option<usize>,
+
+    /// Read the name from a file (first non-comment line), overrides --text
+    #[arg(long)]
+    pub name_file: Option<String>,
+
+    /// Read the email from a file (first non-comment line), overrides --text
+    #[arg(long)]
+    pub email_file: Option<String>,
+
+    /// Read the site from a file (first non-comment line), overrides --text
+    #[arg(long)]
+    pub site_file: Option<String>,
+
+    /// Hash the whole --file content instead of parsing it, identity fields still come from --text
+    #[arg(long, default_value = "false")]
+    pub hash_whole_file: bool,
+
+    /// Extra comment markers for config files, comma separated (e.g. ";,//"); these also end a line
+    /// after whitespace, while the built-in `#` only comments out whole lines
+    #[arg(long)]
+    pub comment_prefixes: Option<String>,
+
+    /// Also print the raw digest in these encodings, comma separated (hex, base64, base58)
+    #[arg(long)]
+    pub also_encode: Option<String>,
+
+    /// Run a self-contained smoke test (parse, generate, validate, save, reload) and exit
+    #[arg(long, default_value = "false")]
+    pub health_check: bool,
+
+    /// Place a symbol at a 0-based position of the password, as <char>@<pos> (e.g. "#@3")
+    #[arg(long)]
+    pub inject_symbol: Option<String>,
+
+    /// How --inject-symbol places the symbol: replace the character there, or shift the rest right
+    #[arg(long)]
+    pub inject_mode: Option<String>,
+
+    /// Save to every --save target or none: stage temp files, rename only if all writes succeed
+    #[arg(long, default_value = "false")]
+    pub atomic_multi_save: bool,
+
+    /// Batch only files modified since the last run, recording modification times in this state file
+    #[arg(long)]
+    pub since_last_run: Option<String>,
+
+    /// Append a check character to the password to catch typos
+    #[arg(long, default_value = "false")]
+    pub check_digit: bool,
+
+    /// Check that a password ends with a valid --check-digit character and exit
+    #[arg(long)]
+    pub verify_check_digit: Option<String>,
+
+    /// Wrap the displayed password at N columns (display only, 0 = no wrapping)
+    #[arg(long, default_value = "0")]
+    pub output_width: usize,
+
+    /// Domain-separation tag hashed ahead of the fields, so another tool hashing the same
+    /// identity fields can't produce the same password (unset keeps existing passwords)
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Age in days past which prune-archived removes archived entries
+    #[arg(long)]
+    pub older_than: Option<u32>,
+
+    /// Serve passwords on this Unix socket, one `<token> <head;tail>` request per line
+    #[arg(long)]
+    pub server: Option<String>,
+
+    /// Token clients must send with each --server request (or PASS_CRAFT_SERVER_TOKEN)
+    #[arg(long)]
+    pub server_token: Option<String>,
+
+    /// Explain where the password's strength comes from and exit
+    #[arg(long, default_value = "false")]
+    pub explain_entropy: bool,
+
+    /// When the password fails --policy-regex, regenerate with an attempt counter appended
+    /// to the base text, up to N times (0 = fail right away)
+    #[arg(long, default_value = "0")]
+    pub policy_retry: usize,
+
+    /// Before saving, compare with the live entry for name,site and refuse a changed password without --yes
+    #[arg(long, default_value = "false")]
+    pub diff_against_saved: bool,
+
+    /// Confirm actions that need it, such as saving a changed password under --diff-against-saved
+    #[arg(long, default_value = "false")]
+    pub yes: bool,
+
+    /// Print every traced setting with its final value and the source that set it, then exit
+    #[arg(long, default_value = "false")]
+    pub trace_provenance: bool,
+
+    /// Retries for network calls after a transient failure, with exponential backoff
+    #[arg(long, default_value = "2")]
+    pub retries: usize,
+
+    /// Format this hex digest as the password, skipping the hashing stage
+    #[arg(long)]
+    pub input_hash: Option<String>,
+}
+
+/// Application configuration
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct AppConfig {
+    // Hash algorithm configuration
+    pub method: String,
+    pub cut_length: usize,
+    // cut:N% keeps N percent of the method's hex digest length, resolved at generation
+    #[serde(default)]
+    pub cut_percent: Option<usize>,
+    // cut:Nbits keeps enough characters for N bits of entropy in the output encoding
+    #[serde(default)]
+    pub cut_bits: Option<usize>,
+    pub end_char: String,
+    pub upper_start: usize,
+    #[serde(default = "default_output_encoding")]
+    pub output_encoding: String,
+    #[serde(default = "default_truncate_unit")]
+    pub truncate_unit: String,
+    // Argon2id costs: memory in KiB, passes, lanes
+    #[serde(default = "default_argon2_memory")]
+    pub argon2_memory: usize,
+    #[serde(default = "default_argon2_iterations")]
+    pub argon2_iterations: usize,
+    #[serde(default = "default_argon2_parallelism")]
+    pub argon2_parallelism: usize,
+    
+    // User information
+    pub name: String,
+    pub email: String,
+    pub site: String,
+    
+    // File configuration
+    pub input_file: Option<String>,
+    pub output_files: Vec<String>,
+    
+    // Platform specific configuration
+    #[serde(default)]
+    pub platform_identifier: String,
+
+    // Rotation counter folded into the base text
+    #[serde(default)]
+    pub counter: Option<usize>,
+    
+    // Output decoration and policy
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub suffix: String,
+    #[serde(default)]
+    pub check_digit: bool,
+    #[serde(default)]
+    pub policy_retry: usize,
+    #[serde(default)]
+    pub inject_symbol: Option<String>,
+    #[serde(default = "default_inject_mode")]
+    pub inject_mode: String,
+    #[serde(default)]
+    pub policy_regex: Option<String>,
+    #[serde(default)]
+    pub allow_emoji: bool,
+    #[serde(default = "default_fields")]
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub field_methods: Vec<(String, String)>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub previous_password: Option<String>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub dedup_fields: bool,
+    #[serde(default)]
+    pub no_transforms: bool,
+    // Where each field was set, keyed by field name; unset fields are defaults
+    #[serde(skip)]
+    pub provenance: Provenance,
+    #[serde(default)]
+    pub only_if_absent: bool,
+    #[serde(default)]
+    pub nonce: Option<String>,
+    // Mixed into the base text so identical identities diverge per user
+    #[serde(default)]
+    pub salt: Option<String>,
+    #[serde(default = "default_pipeline")]
+    pub pipeline: Vec<String>,
+    #[serde(default)]
+    pub canonicalize_site: bool,
+    #[serde(default)]
+    pub normalize_site_case: bool,
+    #[serde(default)]
+    pub unambiguous_fields: bool,
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+    #[serde(default)]
+    pub save_time: bool,
+    #[serde(default)]
+    pub atomic_multi_save: bool,
+    // Whole --file content used as the base text
+    #[serde(skip)]
+    pub whole_file: Option<String>,
+}
+
+/// Origin of a configuration value, reported in validation errors
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConfigSource {
+    #[default]
+    Default,
+    Git,
+    File,
+    Rules,
+    Cli,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ConfigSource::Default => "default",
+            ConfigSource::Git => "git config",
+            ConfigSource::File => "file",
+            ConfigSource::Rules => "site rules",
+            ConfigSource::Cli => "cli",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Field name to source map. Bookkeeping only: configs with equal values compare
+/// equal wherever their values came from.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance(pub HashMap<String, ConfigSource>);
+
+impl PartialEq for Provenance {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+fn default_output_encoding() -> String {
+    "hex".to_string()
+}
+
+fn default_truncate_unit() -> String {
+    "chars".to_string()
+}
+
+fn default_argon2_memory() -> usize {
+    19456
+}
+
+fn default_argon2_iterations() -> usize {
+    2
+}
+
+fn default_argon2_parallelism() -> usize {
+    1
+}
+
+/// Transforms `generate_password_hash` knows, the default pipeline runs them in this order
+const TRANSFORMS: [&str; 3] = ["truncate", "endchar", "case"];
+
+fn default_pipeline() -> Vec<String> {
+    TRANSFORMS.iter().map(|transform| transform.to_string()).collect()
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+fn default_fields() -> Vec<String> {
+    vec!["name".to_string(), "email".to_string(), "site".to_string()]
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            method: "SHA512".to_string(),
+            cut_length: 8,
+            end_char: "!".to_string(),
+            upper_start: 3,
+            output_encoding: default_output_encoding(),
+            truncate_unit: default_truncate_unit(),
+            argon2_memory: default_argon2_memory(),
+            argon2_iterations: default_argon2_iterations(),
+            argon2_parallelism: default_argon2_parallelism(),
+            name: "".to_string(),
+            email: "".to_string(),
+            site: "".to_string(),
+            input_file: None,
+            output_files: Vec::new(),
+            platform_identifier: "".to_string(),
+            counter: None,
+            prefix: "".to_string(),
+            suffix: "".to_string(),
+            policy_regex: None,
+            allow_emoji: false,
+            fields: default_fields(),
+            previous_password: None,
+            strict: false,
+            follow_symlinks: default_follow_symlinks(),
+            dedup_fields: false,
+            no_transforms: false,
+            provenance: Provenance::default(),
+            only_if_absent: false,
+            nonce: None,
+            salt: None,
+            pipeline: default_pipeline(),
+            canonicalize_site: false,
+            unambiguous_fields: false,
+            max_entries: None,
+            save_time: false,
+            whole_file: None,
+            inject_symbol: None,
+            inject_mode: default_inject_mode(),
+            atomic_multi_save: false,
+            check_digit: false,
+            field_methods: Vec::new(),
+            domain: None,
+            normalize_site_case: false,
+            policy_retry: 0,
+            cut_percent: None,
+            cut_bits: None,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn new() -> Result<Self> {
+        Self::from_args(&CliArgs::parse())
+    }
+
+    pub fn from_args(cli_args: &CliArgs) -> Result<Self> {
+        // Set default configuration
+        let mut config = AppConfig::default();
+        
+        // Defaults from git config, overridden by the parameters below
+        if cli_args.from_git {
+            Self::apply_git_config(&mut config, git_config_get);
+        }
+        
+        // Get configuration from various parameters, collecting every malformed token
+        let mut errors = Vec::new();
+        if let Some(text) = &cli_args.text {
+            match Self::apply_text_config(&mut config, text) {
+                Ok(()) => config.mark_source(&token_fields(text), ConfigSource::Cli),
+                Err(e) => errors.push(format!("--text {}", e)),
+            }
+        }
+        
+        if let Some(hash) = &cli_args.hash {
+            match Self::apply_hash_config(&mut config, hash) {
+                Ok(()) => config.mark_source(&token_fields(hash), ConfigSource::Cli),
+                Err(e) => errors.push(format!("--hash {}", e)),
+            }
+        }
+        
+        if let Some(slkv) = &cli_args.slkv {
+            match Self::apply_slkv_config(&mut config, slkv) {
+                Ok(()) => config.mark_source(&token_fields(slkv), ConfigSource::Cli),
+                Err(e) => errors.push(format!("--slkv {}", e)),
+            }
+        }
+        
+        if let Some(sslf) = &cli_args.sslf {
+            match Self::apply_sslf_config(&mut config, sslf) {
+                Ok(()) => config.mark_source(&token_fields(sslf), ConfigSource::Cli),
+                Err(e) => errors.push(format!("--sslf {}", e)),
+            }
+        }
+        
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Malformed parameters: {}", errors.join("; ")));
+        }
+        
+        let comment_prefixes = cli_args.comment_prefixes.as_deref().map(parse_comment_prefixes).unwrap_or_default();
+        
+        // Get configuration from file
+        if let Some(file_path) = &cli_args.file {
+            if cli_args.strict_utf8 {
+                read_text_file(file_path, true)?;
+            }
+            if cli_args.hash_whole_file {
+                config.whole_file = Some(read_text_file(file_path, cli_args.strict_utf8)?);
+            } else {
+                let line_range = cli_args.line_range.as_deref().map(parse_line_range).transpose()?;
+                config = Self::load_from_file(file_path, line_range, &comment_prefixes)?;
+            }
+        }
+        
+        if let Some(truncate_unit) = &cli_args.truncate_unit {
+            config.truncate_unit = truncate_unit.trim().to_lowercase();
+            config.mark_source(&["truncate_unit"], ConfigSource::Cli);
+        }
+        
+        // Hash flags win over --hash and the file
+        Self::apply_hash_flags(&mut config, cli_args);
+        
+        if let Some(prefix) = &cli_args.prefix {
+            config.prefix = prefix.clone();
+            config.mark_source(&["prefix"], ConfigSource::Cli);
+        }
+        if let Some(suffix) = &cli_args.suffix {
+            config.suffix = suffix.clone();
+            config.mark_source(&["suffix"], ConfigSource::Cli);
+        }
+        if let Some(inject_symbol) = &cli_args.inject_symbol {
+            config.inject_symbol = Some(inject_symbol.clone());
+            config.mark_source(&["inject_symbol"], ConfigSource::Cli);
+        }
+        if let Some(inject_mode) = &cli_args.inject_mode {
+            config.inject_mode = inject_mode.trim().to_lowercase();
+            config.mark_source(&["inject_mode"], ConfigSource::Cli);
+        }
+        if let Some(policy_regex) = &cli_args.policy_regex {
+            config.policy_regex = Some(policy_regex.clone());
+            config.mark_source(&["policy_regex"], ConfigSource::Cli);
+        }
+        config.allow_emoji = cli_args.allow_emoji;
+        config.strict = cli_args.strict;
+        if let Some(fields) = &cli_args.fields {
+            config.fields = fields.split(',').map(|field| field.trim().to_lowercase()).collect();
+            config.mark_source(&["fields"], ConfigSource::Cli);
+        }
+        if let Some(domain) = &cli_args.domain {
+            config.domain = Some(domain.clone());
+            config.mark_source(&["domain"], ConfigSource::Cli);
+        }
+        if let Some(field_methods) = &cli_args.field_methods {
+            config.field_methods = parse_field_methods(field_methods)?;
+            config.mark_source(&["field_methods"], ConfigSource::Cli);
+        }
+        if let Some(pipeline) = &cli_args.pipeline {
+            config.pipeline = pipeline.split(',').map(|transform| transform.trim().to_lowercase()).collect();
+            config.mark_source(&["pipeline"], ConfigSource::Cli);
+        }
+        
+        // Field values from files win over every other source
+        if let Some(path) = &cli_args.name_file {
+            config.name = read_field_file(path, cli_args.strict_utf8)?;
+            config.mark_source(&["name"], ConfigSource::File);
+        }
+        if let Some(path) = &cli_args.email_file {
+            config.email = read_field_file(path, cli_args.strict_utf8)?;
+            config.mark_source(&["email"], ConfigSource::File);
+        }
+        if let Some(path) = &cli_args.site_file {
+            config.site = read_field_file(path, cli_args.strict_utf8)?;
+            config.mark_source(&["site"], ConfigSource::File);
+        }
+        
+        config.input_file = cli_args.file.clone();
+        config.output_files = cli_args.save.clone();
+        config.follow_symlinks = !cli_args.no_follow_symlinks;
+        config.dedup_fields = cli_args.dedup_fields;
+        config.no_transforms = cli_args.no_transforms;
+        config.only_if_absent = cli_args.only_if_absent;
+        config.canonicalize_site = cli_args.canonicalize_site;
+        config.normalize_site_case = cli_args.normalize_site_case;
+        config.unambiguous_fields = cli_args.unambiguous_fields;
+        config.max_entries = cli_args.max_entries;
+        config.save_time = cli_args.save_time;
+        config.check_digit = cli_args.check_digit;
+        config.policy_retry = cli_args.policy_retry;
+        config.atomic_multi_save = cli_args.atomic_multi_save;
+        if cli_args.random_nonce {
+            config.nonce = Some(random_nonce()?);
+        } else if let Some(nonce) = &cli_args.nonce {
+            config.nonce = Some(nonce.trim().to_string());
+        }
+        
+        // Apply per-site rules, command line hash parameters still take precedence
+        if let Some(rules_path) = &cli_args.site_rules {
+            let rules = load_site_rules(rules_path, &comment_prefixes);
+            if let Some(rule) = rules.get(&config.site) {
+                debug!("Applying site rule for '{}': {:?}", config.site, rule);
+                rule.apply(&mut config);
+                Self::apply_cli_hash_config(&mut config, cli_args)?;
+            }
+        }
+        
+        // Continue after the previous rotations for name,site in the (first) save file
+        if cli_args.auto_counter {
+            let counter = config.output_files.first()
+                .map(|save_path| next_auto_counter(save_path, &config.name, &config.site))
+                .unwrap_or(0);
+            config.counter = Some(counter);
+        }
+        
+        // Chain from the currently saved password, first rotation has none
+        if cli_args.chain_previous {
+            config.previous_password = config.output_files.first()
+                .and_then(|save_path| latest_saved_password(save_path, &config.name, &config.site));
+        }
+        
+        Ok(config)
+    }
+    
+    pub fn apply_text_config(config: &mut AppConfig, text: &str) -> Result<(), TokenError> {
+        // Parse text configuration format: name:value,email:value,site:value
+        for (index, key, value) in slkv_tokens(text)? {
+            match key {
+                "name" => config.name = value.to_string(),
+                "email" => config.email = value.to_string(),
+                "site" => config.site = value.to_string(),
+                _ => debug!("Ignoring text token {} '{}'", index, key),
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn apply_hash_config(config: &mut AppConfig, hash: &str) -> Result<(), TokenError> {
+        // Parse hash configuration format: method:value,cut:value,end:value,upper-start:value,encoding:value
+        for (index, key, value) in slkv_tokens(hash)? {
+            let number = || value.parse().map_err(|_| TokenError::new(index, &format!("{}:{}", key, value), "expected a number"));
+            match key {
+                "method" => config.method = value.to_string(),
+                "cut" => {
+                    let invalid = || TokenError::new(index, &format!("{}:{}", key, value), "expected a number, percentage or bit count");
+                    if let Some(percent) = value.strip_suffix('%') {
+                        config.cut_percent = Some(percent.trim().parse().map_err(|_| invalid())?);
+                        config.cut_bits = None;
+                    } else if let Some(bits) = value.strip_suffix("bits") {
+                        config.cut_bits = Some(bits.trim().parse().map_err(|_| invalid())?);
+                        config.cut_percent = None;
+                    } else {
+                        config.cut_length = number()?;
+                        config.cut_percent = None;
+                        config.cut_bits = None;
+                    }
+                }
+                "end" => config.end_char = value.to_string(),
+                "upper-start" => config.upper_start = number()?,
+                "encoding" => config.output_encoding = value.to_lowercase(),
+                "memory" => config.argon2_memory = number()?,
+                "iterations" => config.argon2_iterations = number()?,
+                "parallelism" => config.argon2_parallelism = number()?,
+                "salt" => config.salt = Some(match value.strip_prefix("@file:") {
+                    Some(path) => {
+                        let lines = mlt_load_file(path, "");
+                        if lines.is_empty() {
+                            return Err(TokenError::new(index, &format!("{}:{}", key, value), "salt file is missing or empty"));
+                        }
+                        lines.join("\n")
+                    }
+                    None => value.to_string(),
+                }),
+                _ => debug!("Ignoring hash token {} '{}'", index, key),
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn apply_git_config<F>(config: &mut AppConfig, lookup: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(name) = lookup("user.name") {
+            config.name = name;
+            config.mark_source(&["name"], ConfigSource::Git);
+        }
+        if let Some(email) = lookup("user.email") {
+            config.email = email;
+            config.mark_source(&["email"], ConfigSource::Git);
+        }
+    }
+    
+    pub fn apply_cli_hash_config(config: &mut AppConfig, cli_args: &CliArgs) -> Result<(), TokenError> {
+        if let Some(hash) = &cli_args.hash {
+            Self::apply_hash_config(config, hash)?;
+            config.mark_source(&token_fields(hash), ConfigSource::Cli);
+        }
+        
+        if let Some(slkv) = &cli_args.slkv {
+            Self::apply_hash_config(config, slkv)?;
+            config.mark_source(&token_fields(slkv), ConfigSource::Cli);
+        }
+        
+        if let Some(sslf) = &cli_args.sslf {
+            let tail = sslf_get_tail(sslf);
+            Self::apply_hash_config(config, &tail)?;
+            config.mark_source(&token_fields(&tail), ConfigSource::Cli);
+        }
+        
+        Self::apply_hash_flags(config, cli_args);
+        Ok(())
+    }
+    
+    pub fn apply_hash_flags(config: &mut AppConfig, cli_args: &CliArgs) {
+        if let Some(method) = &cli_args.method {
+            config.method = method.trim().to_string();
+            config.mark_source(&["method"], ConfigSource::Cli);
+        }
+        if let Some(cut) = cli_args.cut {
+            config.cut_length = cut;
+            config.cut_percent = None;
+            config.cut_bits = None;
+            config.mark_source(&["cut_length"], ConfigSource::Cli);
+        }
+        if let Some(end) = &cli_args.end {
+            config.end_char = end.clone();
+            config.mark_source(&["end_char"], ConfigSource::Cli);
+        }
+        if let Some(upper_start) = cli_args.upper_start {
+            config.upper_start = upper_start;
+            config.mark_source(&["upper_start"], ConfigSource::Cli);
+        }
+        if let Some(salt) = &cli_args.salt {
+            config.salt = Some(salt.clone());
+            config.mark_source(&["salt"], ConfigSource::Cli);
+        }
+    }
+    
+    /// Copy with a `cut:N%` resolved to N percent of the method's hex digest length
+    /// (rounded down), so switching algorithms keeps the proportion. A `cut:Nbits`
+    /// resolves to the fewest characters carrying N bits in the output encoding.
+    pub fn with_resolved_cut(&self) -> AppConfig {
+        let mut config = self.clone();
+        if let (Some(percent), Some(algorithm)) = (self.cut_percent, find_algorithm(&self.method)) {
+            config.cut_length = algorithm.output_bytes * 2 * percent / 100;
+            config.cut_percent = None;
+        }
+        if let Some(bits) = self.cut_bits {
+            config.cut_length = bits.div_ceil(self.bits_per_char());
+            config.cut_bits = None;
+        }
+        config
+    }
+    
+    /// Entropy bits per output character, or per byte under byte truncation
+    pub fn bits_per_char(&self) -> usize {
+        match (self.truncate_unit.as_str(), self.output_encoding.as_str()) {
+            (_, "emoji") | ("bytes", _) => 8,
+            (_, "base64") => 6,
+            (_, "zbase32") => 5,
+            _ => 4,
+        }
+    }
+    
+    /// Argon2id with cut:0 outputs the verifiable PHC string, untruncated and untransformed
+    pub fn emits_phc(&self) -> bool {
+        self.method.eq_ignore_ascii_case("ARGON2ID") && self.cut_length == 0 && self.cut_percent.is_none() && self.cut_bits.is_none()
+    }
+    
+    /// Hash method for an identity field under --field-methods, the global method if unassigned
+    pub fn field_method(&self, field: &str) -> &str {
+        self.field_methods.iter()
+            .find(|(name, _)| name == field)
+            .map_or(self.method.as_str(), |(_, method)| method.as_str())
+    }
+    
+    /// Record the source of the given fields
+    pub fn mark_source(&mut self, fields: &[&str], source: ConfigSource) {
+        for field in fields {
+            self.provenance.0.insert(field.to_string(), source);
+        }
+    }
+    
+    /// Source of a field, default when nothing set it
+    pub fn source_of(&self, field: &str) -> ConfigSource {
+        self.provenance.0.get(field).copied().unwrap_or_default()
+    }
+    
+    /// (field, final value, source) for every setting whose source is tracked
+    pub fn trace_provenance(&self) -> Vec<(&'static str, String, ConfigSource)> {
+        let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+        let values = [
+            ("method", self.method.clone()),
+            ("cut_length", self.cut_length.to_string()),
+            ("end_char", self.end_char.clone()),
+            ("upper_start", self.upper_start.to_string()),
+            ("output_encoding", self.output_encoding.clone()),
+            ("truncate_unit", self.truncate_unit.clone()),
+            ("argon2_memory", self.argon2_memory.to_string()),
+            ("argon2_iterations", self.argon2_iterations.to_string()),
+            ("argon2_parallelism", self.argon2_parallelism.to_string()),
+            ("name", self.name.clone()),
+            ("email", self.email.clone()),
+            ("site", self.site.clone()),
+            ("fields", self.fields.join(",")),
+            ("field_methods", self.field_methods.iter().map(|(field, method)| format!("{}:{}", field, method)).collect::<Vec<_>>().join(",")),
+            ("domain", optional(&self.domain)),
+            ("salt", optional(&self.salt)),
+            ("pipeline", self.pipeline.join(",")),
+            ("prefix", self.prefix.clone()),
+            ("suffix", self.suffix.clone()),
+            ("inject_symbol", optional(&self.inject_symbol)),
+            ("inject_mode", self.inject_mode.clone()),
+            ("policy_regex", optional(&self.policy_regex)),
+        ];
+        values.into_iter()
+            .map(|(field, value)| (field, value, self.source_of(field)))
+            .collect()
+    }
+    
+    pub fn apply_slkv_config(config: &mut AppConfig, slkv: &str) -> Result<(), TokenError> {
+        Self::apply_text_config(config, slkv)?;
+        Self::apply_hash_config(config, slkv)
+    }
+    
+    pub fn apply_sslf_config(config: &mut AppConfig, sslf: &str) -> Result<(), TokenError> {
+        let head = sslf_get_head(sslf);
+        let tail = sslf_get_tail(sslf);
+        
+        Self::apply_text_config(config, &head)?;
+        Self::apply_hash_config(config, &tail)
+    }
+    
+    pub fn load_from_file(file_path: &str, line_range: Option<(usize, usize)>, comment_prefixes: &[String]) -> Result<Self> {
+        if file_path.ends_with(".toml") {
+            return Self::from_toml(&fs::read_to_string(file_path)?);
+        }
+
+        if let Some(warning) = save_file_warning(file_path) {
+            info_status(&warning, 2);
+        }
+
+        // let content = fs::read_to_string(file_path)?;
+        // let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+        
+        // 过滤空行和只包含空白字符的行
+        // let lines: Vec<String> = content
+        //     .lines()
+        //     .map(|s| s.trim())  // 去除前后空白
+        //     .filter(|line| !line.is_empty())  // 过滤空行
+        //     .map(|s| s.to_string())
+        //     .collect();
+
+        // 使用现有的sslf_load_file函数，它会清理注释和空行
+        let lines = sslf_load_file(file_path, "", comment_prefixes);
+        let lines = match line_range {
+            Some(line_range) => select_line_range(&lines, line_range)?,
+            None => lines,
+        };
+
+        if let Some(last_line) = lines.last() {
+            let mut config = AppConfig::default();
+            Self::apply_sslf_config(&mut config, last_line)
+                .map_err(|e| anyhow::anyhow!("{}: {}", file_path, e))?;
+            config.mark_source(&token_fields(last_line), ConfigSource::File);
+            Ok(config)
+        } else {
+            debug!("配置文件 '{}' 为空或没有有效内容，使用默认配置", file_path);
+            Ok(AppConfig::default())
+        }
+    }
+    
+    /// Serialize the hash and user settings as flat TOML
+    pub fn to_toml(&self) -> String {
+        let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+        
+        let mut lines = vec!["# pass-craft configuration".to_string()];
+        lines.push(format!("method = {}", quote(&self.method)));
+        lines.push(format!("cut_length = {}", self.cut_length));
+        if let Some(cut_percent) = self.cut_percent {
+            lines.push(format!("cut_percent = {}", cut_percent));
+        }
+        if let Some(cut_bits) = self.cut_bits {
+            lines.push(format!("cut_bits = {}", cut_bits));
+        }
+        lines.push(format!("end_char = {}", quote(&self.end_char)));
+        lines.push(format!("upper_start = {}", self.upper_start));
+        lines.push(format!("output_encoding = {}", quote(&self.output_encoding)));
+        lines.push(format!("truncate_unit = {}", quote(&self.truncate_unit)));
+        if self.method.eq_ignore_ascii_case("ARGON2ID") {
+            lines.push(format!("argon2_memory = {}", self.argon2_memory));
+            lines.push(format!("argon2_iterations = {}", self.argon2_iterations));
+            lines.push(format!("argon2_parallelism = {}", self.argon2_parallelism));
+        }
+        lines.push(format!("name = {}", quote(&self.name)));
+        lines.push(format!("email = {}", quote(&self.email)));
+        lines.push(format!("site = {}", quote(&self.site)));
+        // The --hash-whole-file content replaces the base text, a digest stands in for it
+        if let Some(whole_file) = &self.whole_file {
+            lines.push(format!("whole_file_sha256 = {}", quote(&hex_encode(&Sha256::digest(whole_file.as_bytes())))));
+        }
+        if let Some(counter) = self.counter {
+            lines.push(format!("counter = {}", counter));
+        }
+        if let Some(previous_password) = &self.previous_password {
+            lines.push(format!("previous_password = {}", quote(previous_password)));
+        }
+        if !self.prefix.is_empty() {
+            lines.push(format!("prefix = {}", quote(&self.prefix)));
+        }
+        if !self.suffix.is_empty() {
+            lines.push(format!("suffix = {}", quote(&self.suffix)));
+        }
+        if self.fields != default_fields() {
+            lines.push(format!("fields = {}", quote(&self.fields.join(","))));
+        }
+        if let Some(domain) = &self.domain {
+            lines.push(format!("domain = {}", quote(domain)));
+        }
+        if !self.field_methods.is_empty() {
+            let pairs: Vec<String> = self.field_methods.iter().map(|(field, method)| format!("{}:{}", field, method)).collect();
+            lines.push(format!("field_methods = {}", quote(&pairs.join(","))));
+        }
+        if let Some(nonce) = &self.nonce {
+            lines.push(format!("nonce = {}", quote(nonce)));
+        }
+        if let Some(salt) = &self.salt {
+            lines.push(format!("salt = {}", quote(salt)));
+        }
+        if let Some(inject_symbol) = &self.inject_symbol {
+            lines.push(format!("inject_symbol = {}", quote(inject_symbol)));
+            lines.push(format!("inject_mode = {}", quote(&self.inject_mode)));
+        }
+        if self.pipeline != default_pipeline() {
+            lines.push(format!("pipeline = {}", quote(&self.pipeline.join(","))));
+        }
+        if self.dedup_fields {
+            lines.push("dedup_fields = true".to_string());
+        }
+        if self.no_transforms {
+            lines.push("no_transforms = true".to_string());
+        }
+        if self.canonicalize_site {
+            lines.push("canonicalize_site = true".to_string());
+        }
+        if self.normalize_site_case {
+            lines.push("normalize_site_case = true".to_string());
+        }
+        if self.unambiguous_fields {
+            lines.push("unambiguous_fields = true".to_string());
+        }
+        if self.check_digit {
+            lines.push("check_digit = true".to_string());
+        }
+        if self.policy_retry > 0 {
+            lines.push(format!("policy_retry = {}", self.policy_retry));
+        }
+        lines.join("\n") + "\n"
+    }
+    
+    /// Parse flat TOML written by `to_toml` (key = "string" or key = integer)
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut config = AppConfig::default();
+        
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("TOML line {}: expected key = value", index + 1))?;
+            let value = value.trim();
+            let string_value = || -> Result<String> {
+                value.strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .map(|value| value.replace("\\\"", "\"").replace("\\\\", "\\"))
+                    .ok_or_else(|| anyhow::anyhow!("TOML line {}: expected a quoted string", index + 1))
+            };
+            let number_value = || -> Result<usize> {
+                value.parse()
+                    .map_err(|_| anyhow::anyhow!("TOML line {}: expected an integer", index + 1))
+            };
+            let bool_value = || -> Result<bool> {
+                value.parse()
+                    .map_err(|_| anyhow::anyhow!("TOML line {}: expected true or false", index + 1))
+            };
+            
+            match key.trim() {
+                "method" => config.method = string_value()?,
+                "cut_length" => config.cut_length = number_value()?,
+                "cut_percent" => config.cut_percent = Some(number_value()?),
+                "cut_bits" => config.cut_bits = Some(number_value()?),
+                "end_char" => config.end_char = string_value()?,
+                "upper_start" => config.upper_start = number_value()?,
+                "output_encoding" => config.output_encoding = string_value()?,
+                "truncate_unit" => config.truncate_unit = string_value()?,
+                "argon2_memory" => config.argon2_memory = number_value()?,
+                "argon2_iterations" => config.argon2_iterations = number_value()?,
+                "argon2_parallelism" => config.argon2_parallelism = number_value()?,
+                "name" => config.name = string_value()?,
+                "email" => config.email = string_value()?,
+                "site" => config.site = string_value()?,
+                "counter" => config.counter = Some(number_value()?),
+                "previous_password" => config.previous_password = Some(string_value()?),
+                "prefix" => config.prefix = string_value()?,
+                "suffix" => config.suffix = string_value()?,
+                "fields" => config.fields = string_value()?.split(',').map(|field| field.trim().to_string()).collect(),
+                "domain" => config.domain = Some(string_value()?),
+                "field_methods" => config.field_methods = parse_field_methods(&string_value()?)?,
+                "nonce" => config.nonce = Some(string_value()?),
+                "salt" => config.salt = Some(string_value()?),
+                "inject_symbol" => config.inject_symbol = Some(string_value()?),
+                "inject_mode" => config.inject_mode = string_value()?,
+                "pipeline" => config.pipeline = string_value()?.split(',').map(|transform| transform.trim().to_string()).collect(),
+                "dedup_fields" => config.dedup_fields = bool_value()?,
+                "no_transforms" => config.no_transforms = bool_value()?,
+                "canonicalize_site" => config.canonicalize_site = bool_value()?,
+                "normalize_site_case" => config.normalize_site_case = bool_value()?,
+                "unambiguous_fields" => config.unambiguous_fields = bool_value()?,
+                "check_digit" => config.check_digit = bool_value()?,
+                "policy_retry" => config.policy_retry = number_value()?,
+                "whole_file_sha256" => debug!("Ignoring TOML line {}: whole-file content comes from --hash-whole-file", index + 1),
+                other => return Err(anyhow::anyhow!("TOML line {}: unknown key '{}'", index + 1, other)),
+            }
+            config.mark_source(&[key.trim()], ConfigSource::File);
+        }
+        
+        Ok(config)
+    }
+    
+    /// Display configuration information (for --show-config)
+    pub fn display_config(&self) {
+        info_step("Password Hash Generator Configuration", 60, '=');
+        
+        // User information configuration
+        println!("👤 User Information:");
+        println!("  Name: {}", self.name);
+        println!("  Email: {}", self.email);
+        println!("  Site: {}", self.site);
+        
+        // Hash algorithm configuration
+        println!("🔑 Hash Algorithm Configuration:");
+        println!("  Method: {}", self.method);
+        println!("  Cut Length: {}", self.cut_length);
+        println!("  End Character: {}", self.end_char);
+        println!("  Upper Start: {}", self.upper_start);
+        println!("  Output Encoding: {}", self.output_encoding);
+        println!("  Truncate Unit: {}", self.truncate_unit);
+        println!("  Prefix: {}", self.prefix);
+        println!("  Suffix: {}", self.suffix);
+        println!("  Policy Regex: {}", self.policy_regex.as_deref().unwrap_or("Not set"));
+        if let Some(counter) = self.counter {
+            println!("  Counter: {}", counter);
+        }
+        
+        // File configuration
+        println!("📁 File Configuration:");
+        println!("  Input File: {}", self.input_file.as_deref().unwrap_or("Not set"));
+        if self.output_files.is_empty() {
+            println!("  Output File: Not set");
+        }
+        for output_file in &self.output_files {
+            println!("  Output File: {}", output_file);
+        }
+        
+        // Platform configuration
+        println!("🔧 Platform Configuration:");
+        println!("  Platform Identifier: {}", self.platform_identifier);
+        
+        // Configuration validation status
+        println!("✅ Configuration Validation:");
+        match self.validate() {
+            Ok(()) => info_status("Status: Valid", 0),
+            Err(e) => info_status(&format!("Status: Invalid - {}", e), 1),
+        }
+    }
+    
+    /// Validate configuration
+    pub fn validate(&self) -> Result<()> {
+        if find_algorithm(&self.method).is_none() {
+            return Err(anyhow::anyhow!("Unsupported hash algorithm: {} (from {})", self.method, self.source_of("method")));
+        }
+        
+        if let Some(percent) = self.cut_percent {
+            if percent == 0 || percent > 100 {
+                return Err(anyhow::anyhow!("Cut percentage must be between 1-100, got {}%", percent));
+            }
+            let resolved = self.with_resolved_cut();
+            if resolved.cut_length == 0 || resolved.cut_length > 64 {
+                return Err(anyhow::anyhow!("Cut {}% of {} resolves to {} chars, must be between 1-64", percent, self.method, resolved.cut_length));
+            }
+            return resolved.validate();
+        }
+        
+        if let (Some(bits), Some(algorithm)) = (self.cut_bits, find_algorithm(&self.method)) {
+            // Extendable-output algorithms squeeze as many bits as the cut needs
+            let digest_bits = algorithm.output_bytes * 8;
+            if bits == 0 || (!algorithm.xof && bits > digest_bits) {
+                return Err(anyhow::anyhow!("Cut {}bits must be between 1 and the {} bits {} supplies", bits, digest_bits, self.method));
+            }
+            let resolved = self.with_resolved_cut();
+            if resolved.cut_length > 64 {
+                return Err(anyhow::anyhow!("Cut {}bits resolves to {} chars, must be between 1-64", bits, resolved.cut_length));
+            }
+            return resolved.validate();
+        }
+        
+        if self.method.eq_ignore_ascii_case("ARGON2ID") {
+            if !(1..=64).contains(&self.argon2_parallelism) {
+                return Err(anyhow::anyhow!("Argon2id parallelism must be between 1-64, got {}", self.argon2_parallelism));
+            }
+            // 8 KiB per lane is the Argon2 minimum, 4 GiB is well beyond any sane derivation
+            if self.argon2_memory < 8 * self.argon2_parallelism || self.argon2_memory > 4 * 1024 * 1024 {
+                return Err(anyhow::anyhow!("Argon2id memory must be between {} KiB and 4 GiB, got {} KiB", 8 * self.argon2_parallelism, self.argon2_memory));
+            }
+            if !(1..=100).contains(&self.argon2_iterations) {
+                return Err(anyhow::anyhow!("Argon2id iterations must be between 1-100, got {}", self.argon2_iterations));
+            }
+            argon2id_salt(self)?;
+        }
+        
+        if !self.emits_phc() && (self.cut_length == 0 || self.cut_length > 64) {
+            return Err(anyhow::anyhow!("Cut length must be between 1-64, got {} (from {})", self.cut_length, self.source_of("cut_length")));
+        }
+        
+        if self.fields.is_empty() || self.fields.iter().any(|field| !["name", "email", "site"].contains(&field.as_str())) {
+            return Err(anyhow::anyhow!("Fields must be a list of name, email, site: {}", self.fields.join(",")));
+        }
+        if (1..self.fields.len()).any(|i| self.fields[..i].contains(&self.fields[i])) {
+            return Err(anyhow::anyhow!("Duplicate field in: {}", self.fields.join(",")));
+        }
+        // The newline separates the tag from the fields, which never contain one
+        if self.domain.as_ref().is_some_and(|domain| domain.is_empty() || domain.contains('\n')) {
+            return Err(anyhow::anyhow!("Domain tag must be non-empty and on one line"));
+        }
+        
+        for (field, method) in &self.field_methods {
+            if !["name", "email", "site"].contains(&field.as_str()) {
+                return Err(anyhow::anyhow!("Field method for unknown field '{}', expected name, email or site", field));
+            }
+            if find_algorithm(method).is_none() || method.eq_ignore_ascii_case("ARGON2ID") {
+                return Err(anyhow::anyhow!("Unsupported hash algorithm for field {}: {}", field, method));
+            }
+        }
+        
+        if !OUTPUT_ENCODINGS.contains(&self.output_encoding.as_str()) {
+            return Err(anyhow::anyhow!("Unknown output encoding '{}', expected one of: {}", self.output_encoding, OUTPUT_ENCODINGS.join(", ")));
+        }
+        
+        if self.output_encoding == "emoji" && !self.allow_emoji {
+            return Err(anyhow::anyhow!("Emoji output encoding requires --allow-emoji, many sites reject emoji passwords"));
+        }
+        
+        if !["bytes", "chars"].contains(&self.truncate_unit.as_str()) {
+            return Err(anyhow::anyhow!("Truncate unit must be bytes or chars"));
+        }
+        
+        if let Some(transform) = self.pipeline.iter().find(|transform| !TRANSFORMS.contains(&transform.as_str())) {
+            return Err(anyhow::anyhow!("Unknown transform '{}', expected one of: {}", transform, TRANSFORMS.join(", ")));
+        }
+        // Byte truncation re-encodes a slice of the digest, so it can't follow other transforms
+        if (self.truncate_unit == "bytes" || self.output_encoding == "emoji")
+            && self.pipeline.iter().position(|transform| transform == "truncate").is_some_and(|position| position > 0) {
+            return Err(anyhow::anyhow!("Byte truncation must be the first transform in the pipeline"));
+        }
+        
+        if !self.emits_phc() && self.upper_start > self.cut_length {
+            return Err(anyhow::anyhow!(
+                "Upper start position {} (from {}) cannot exceed cut length {} (from {})",
+                self.upper_start, self.source_of("upper_start"),
+                self.cut_length, self.source_of("cut_length")));
+        }
+        
+        if let Some(spec) = &self.inject_symbol {
+            let (_, position) = parse_inject_symbol(spec)?;
+            if !INJECT_MODES.contains(&self.inject_mode.as_str()) {
+                return Err(anyhow::anyhow!("Unknown inject mode '{}', expected one of: {}", self.inject_mode, INJECT_MODES.join(", ")));
+            }
+            // Char truncation fixes the length up front, byte truncation is checked at generation
+            if self.truncate_unit == "chars" && self.output_encoding != "emoji" && !self.emits_phc() && position >= self.cut_length {
+                return Err(anyhow::anyhow!("Inject position {} is outside the {} character output", position, self.cut_length));
+            }
+        }
+        
+        if let Some(policy_regex) = &self.policy_regex {
+            Regex::new(policy_regex)
+                .map_err(|e| anyhow::anyhow!("Invalid policy regex: {}", e))?;
+        }
+        
+        if self.strict {
+            if let Some(warning) = self.warnings().first() {
+                return Err(anyhow::anyhow!("{} (strict mode)", warning));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Configuration warnings, errors under --strict
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        
+        // The end character replaces one output character; on short outputs that
+        // leaves little hash-derived text, so warn above END_CHAR_MAX_FRACTION
+        if !self.end_char.is_empty() && self.cut_length > 0
+            && 1.0 / self.cut_length as f64 > END_CHAR_MAX_FRACTION {
+            warnings.push(format!(
+                "End character replaces 1 of {} characters, use a cut length of at least {}",
+                self.cut_length, (1.0 / END_CHAR_MAX_FRACTION) as usize));
+        }
+        
+        // Only the first character replaces the last output character, the rest is dropped
+        if self.end_char.chars().count() > 1 {
+            let first = self.end_char.chars().next().unwrap_or_default();
+            warnings.push(format!("End character '{}' has more than one character, only '{}' is used", self.end_char, first));
+        }
+        
+        warnings
+    }
+}
+
+/// Largest share of the output the end character may replace without a warning
+const END_CHAR_MAX_FRACTION: f64 = 0.25;
+
+/// Registered hash algorithm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlgorithmInfo {
+    pub name: &'static str,
+    pub output_bytes: usize,
+    pub weak: bool,
+    // Cryptographic hash, as opposed to a checksum
+    pub crypto: bool,
+    // Extra inputs the algorithm takes: "salt", "key", "cost"
+    pub params: &'static [&'static str],
+    // Extendable output: `output_bytes` is only the default length, generation squeezes what cut_length needs
+    pub xof: bool,
+}
+
+impl AlgorithmInfo {
+    /// JSON object describing the algorithm
+    pub fn to_json(&self) -> String {
+        let accepts = |param: &str| self.params.contains(&param);
+        format!(
+            "{{\"name\": \"{}\", \"output_bytes\": {}, \"weak\": {}, \"crypto\": {}, \"xof\": {}, \"accepts_salt\": {}, \"accepts_key\": {}, \"accepts_cost\": {}}}",
+            self.name, self.output_bytes, self.weak, self.crypto, self.xof, accepts("salt"), accepts("key"), accepts("cost"))
+    }
+}
+
+/// Supported hash algorithms
+pub const ALGORITHMS: [AlgorithmInfo; 10] = [
+    AlgorithmInfo { name: "MD5", output_bytes: 16, weak: true, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "SHA1", output_bytes: 20, weak: true, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "SHA256", output_bytes: 32, weak: false, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "SHA512", output_bytes: 64, weak: false, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "SHA3-256", output_bytes: 32, weak: false, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "SHA3-512", output_bytes: 64, weak: false, crypto: true, params: &[], xof: false },
+    AlgorithmInfo { name: "BLAKE3", output_bytes: 32, weak: false, crypto: true, params: &[], xof: false },
+    // SHAKE output length is driven by cut_length
+    AlgorithmInfo { name: "SHAKE128", output_bytes: 32, weak: false, crypto: true, params: &[], xof: true },
+    AlgorithmInfo { name: "SHAKE256", output_bytes: 64, weak: false, crypto: true, params: &[], xof: true },
+    // cut:0 emits the full PHC string instead of a password
+    AlgorithmInfo { name: "ARGON2ID", output_bytes: 32, weak: false, crypto: true, params: &["salt", "cost"], xof: false },
+];
+
+/// Registered algorithms sorted by name, the order every listing uses
+pub fn sorted_algorithms() -> Vec<&'static AlgorithmInfo> {
+    let mut algorithms: Vec<&AlgorithmInfo> = ALGORITHMS.iter().collect();
+    algorithms.sort_by_key(|algorithm| algorithm.name);
+    algorithms
+}
+
+/// The whole registry as a JSON array, sorted by name
+pub fn registry_json() -> String {
+    let entries: Vec<String> = sorted_algorithms().into_iter().map(AlgorithmInfo::to_json).collect();
+    format!("[{}]", entries.join(", "))
+}
+
+/// One line per algorithm, sorted by name: name, digest size and weak/xof markers
+pub fn list_algorithms() -> String {
+    sorted_algorithms().into_iter()
+        .map(|algorithm| {
+            let mut line = format!("{:<10} {:>2} bytes", algorithm.name, algorithm.output_bytes);
+            if algorithm.weak {
+                line.push_str(" weak");
+            }
+            if algorithm.xof {
+                line.push_str(" xof");
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Find a registered algorithm by case-insensitive name
+pub fn find_algorithm(name: &str) -> Option<&'static AlgorithmInfo> {
+    ALGORITHMS.iter().find(|algorithm| algorithm.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Hash configuration overrides for a single site
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HashConfig {
+    pub method: Option<String>,
+    pub cut_length: Option<usize>,
+    pub end_char: Option<String>,
+    pub upper_start: Option<usize>,
+}
+
+impl HashConfig {
+    pub fn parse(hash: &str) -> Self {
+        let get = |key: &str| Some(slkv_get(key, hash, false)).filter(|value| !value.is_empty());
+        
+        Self {
+            method: get("method"),
+            cut_length: get("cut").and_then(|value| value.parse().ok()),
+            end_char: get("end"),
+            upper_start: get("upper-start").and_then(|value| value.parse().ok()),
+        }
+    }
+    
+    pub fn apply(&self, config: &mut AppConfig) {
+        if let Some(method) = &self.method {
+            config.method = method.clone();
+            config.mark_source(&["method"], ConfigSource::Rules);
+        }
+        if let Some(cut_length) = self.cut_length {
+            config.cut_length = cut_length;
+            config.cut_percent = None;
+            config.cut_bits = None;
+            config.mark_source(&["cut_length"], ConfigSource::Rules);
+        }
+        if let Some(end_char) = &self.end_char {
+            config.end_char = end_char.clone();
+            config.mark_source(&["end_char"], ConfigSource::Rules);
+        }
+        if let Some(upper_start) = self.upper_start {
+            config.upper_start = upper_start;
+            config.mark_source(&["upper_start"], ConfigSource::Rules);
+        }
+    }
+}
+
+// Load per-site hash rules, one site;hash-parameters line per site
+pub fn load_site_rules(loc: &str, comment_prefixes: &[String]) -> HashMap<String, HashConfig> {
+    sslf_load_file(loc, "", comment_prefixes)
+        .iter()
+        .map(|line| (sslf_get_head(line), HashConfig::parse(&sslf_get_tail(line))))
+        .filter(|(site, _)| !site.is_empty())
+        .collect()
+}
+
+// Calculate string digest bytes
+pub fn get_string_digest(string: &str, hash_name: &str) -> Result<Vec<u8>> {
+    let hash_name = hash_name.to_uppercase();
+    
+    Ok(match hash_name.as_str() {
+        "MD5" => md5::compute(string.as_bytes()).0.to_vec(),
+        "SHA1" => Sha1::digest(string.as_bytes()).to_vec(),
+        "SHA256" => Sha256::digest(string.as_bytes()).to_vec(),
+        "SHA512" => Sha512::digest(string.as_bytes()).to_vec(),
+        "SHA3-256" => Sha3_256::digest(string.as_bytes()).to_vec(),
+        "SHA3-512" => Sha3_512::digest(string.as_bytes()).to_vec(),
+        "BLAKE3" => blake3::hash(string.as_bytes()).as_bytes().to_vec(),
+        "ARGON2ID" => return Err(anyhow::anyhow!("Argon2id needs configured costs and a salt, use get_method_digest")),
+        _ => find_algorithm(&hash_name)
+            .and_then(|algorithm| shake_digest(&hash_name, string.as_bytes(), algorithm.output_bytes))
+            .ok_or_else(|| anyhow::anyhow!("Unsupported hash algorithm: {}", hash_name))?,
+    })
+}
+
+// SHAKE128/SHAKE256 digest squeezed to `len` bytes, None for other methods
+fn shake_digest(method: &str, data: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut output = vec![0u8; len];
+    match method.trim().to_uppercase().as_str() {
+        "SHAKE128" => Shake128::digest_xof(data, &mut output),
+        "SHAKE256" => Shake256::digest_xof(data, &mut output),
+        _ => return None,
+    }
+    Some(output)
+}
+
+// Squeeze `len` bytes from an extendable-output hasher
+fn squeeze<X: ExtendableOutput>(hasher: X, len: usize) -> Vec<u8> {
+    let mut output = vec![0u8; len];
+    hasher.finalize_xof_into(&mut output);
+    output
+}
+
+/// Full digest bytes for `method`, taking Argon2id's costs and salt from `config`
+pub fn get_method_digest(string: &str, method: &str, config: &AppConfig) -> Result<Vec<u8>> {
+    if method.trim().eq_ignore_ascii_case("ARGON2ID") {
+        return argon2id_digest(string.as_bytes(), config);
+    }
+    get_string_digest(string, method)
+}
+
+/// Digest bytes for generation: extendable-output algorithms squeeze exactly what the
+/// truncation keeps, fixed-length ones return their full digest
+pub fn get_config_digest(string: &str, config: &AppConfig) -> Result<Vec<u8>> {
+    match shake_digest(&config.method, string.as_bytes(), xof_output_len(config)) {
+        Some(digest) => Ok(digest),
+        None => get_method_digest(string, &config.method, config),
+    }
+}
+
+// Digest bytes an extendable-output algorithm squeezes so truncation has enough to keep
+fn xof_output_len(config: &AppConfig) -> usize {
+    match (config.truncate_unit.as_str(), config.output_encoding.as_str()) {
+        (_, "emoji") | ("bytes", _) => config.cut_length,
+        (_, "base64") => (config.cut_length * 3).div_ceil(4),
+        (_, "zbase32") => (config.cut_length * 5).div_ceil(8),
+        _ => config.cut_length.div_ceil(2),
+    }
+}
+
+/// Incremental hashing for input that arrives in chunks, mirroring the `Digest` pattern:
+/// `update` as data comes in, then `finalize` applies the same transforms as generation
+pub struct HashBuilder {
+    method: String,
+    state: HashState,
+    // Argon2id costs and salt for `digest`
+    config: AppConfig,
+}
+
+enum HashState {
+    Md5(md5::Context),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_512(Sha3_512),
+    Shake128(Shake128),
+    Shake256(Shake256),
+    Blake3(Box<blake3::Hasher>),
+    // Argon2id isn't incremental, the input is buffered until finish
+    Argon2id(Vec<u8>),
+}
+
+impl HashBuilder {
+    pub fn new(method: &str) -> Result<Self> {
+        Self::with_config(&AppConfig { method: method.to_string(), ..AppConfig::default() })
+    }
+    
+    /// Builder for the configured method, Argon2id uses the configured costs and salt
+    pub fn with_config(config: &AppConfig) -> Result<Self> {
+        let method = config.method.as_str();
+        let state = match method.to_uppercase().as_str() {
+            "MD5" => HashState::Md5(md5::Context::new()),
+            "SHA1" => HashState::Sha1(Sha1::new()),
+            "SHA256" => HashState::Sha256(Sha256::new()),
+            "SHA512" => HashState::Sha512(Sha512::new()),
+            "SHA3-256" => HashState::Sha3_256(Sha3_256::new()),
+            "SHA3-512" => HashState::Sha3_512(Sha3_512::new()),
+            "SHAKE128" => HashState::Shake128(Shake128::default()),
+            "SHAKE256" => HashState::Shake256(Shake256::default()),
+            "BLAKE3" => HashState::Blake3(Box::default()),
+            "ARGON2ID" => HashState::Argon2id(Vec::new()),
+            other => return Err(anyhow::anyhow!("Unsupported hash algorithm: {}", other)),
+        };
+        Ok(Self { method: method.to_string(), state, config: config.clone() })
+    }
+    
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.state {
+            HashState::Md5(context) => context.consume(data),
+            HashState::Sha1(hasher) => hasher.update(data),
+            HashState::Sha256(hasher) => hasher.update(data),
+            HashState::Sha512(hasher) => hasher.update(data),
+            HashState::Sha3_256(hasher) => hasher.update(data),
+            HashState::Sha3_512(hasher) => hasher.update(data),
+            HashState::Shake128(hasher) => sha3::digest::Update::update(hasher, data),
+            HashState::Shake256(hasher) => sha3::digest::Update::update(hasher, data),
+            HashState::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            HashState::Argon2id(buffer) => buffer.extend_from_slice(data),
+        }
+    }
+    
+    // Digest bytes, extendable-output algorithms squeeze `xof_len`, Argon2id uses `config`
+    fn finish(self, xof_len: usize, config: &AppConfig) -> Result<Vec<u8>> {
+        Ok(match self.state {
+            HashState::Md5(context) => context.compute().0.to_vec(),
+            HashState::Sha1(hasher) => hasher.finalize().to_vec(),
+            HashState::Sha256(hasher) => hasher.finalize().to_vec(),
+            HashState::Sha512(hasher) => hasher.finalize().to_vec(),
+            HashState::Sha3_256(hasher) => hasher.finalize().to_vec(),
+            HashState::Sha3_512(hasher) => hasher.finalize().to_vec(),
+            HashState::Shake128(hasher) => squeeze(hasher, xof_len),
+            HashState::Shake256(hasher) => squeeze(hasher, xof_len),
+            HashState::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+            HashState::Argon2id(buffer) => argon2id_digest(&buffer, config)?,
+        })
+    }
+    
+    /// Full digest, extendable-output algorithms use their registry output length
+    pub fn digest(self) -> Result<Vec<u8>> {
+        let xof_len = find_algorithm(&self.method).map_or(0, |algorithm| algorithm.output_bytes);
+        let config = self.config.clone();
+        self.finish(xof_len, &config)
+    }
+    
+    /// Password for the hashed input, formatted by `config` (its method is ignored,
+    /// its Argon2id costs and salt are not)
+    pub fn finalize(self, config: &AppConfig) -> Result<String> {
+        let config = &config.with_resolved_cut();
+        let digest = self.finish(xof_output_len(config), config)?;
+        if config.no_transforms {
+            return Ok(hex_encode(&digest));
+        }
+        format_digest(&digest, config)
+    }
+}
+
+/// Argon2id (RFC 9106, version 0x13) tag of `len` bytes; `memory` is in KiB
+pub fn argon2id(password: &[u8], salt: &[u8], memory: usize, iterations: usize, parallelism: usize, len: usize) -> Result<Vec<u8>> {
+    let cost = |value: usize| u32::try_from(value).map_err(|_| anyhow::anyhow!("Argon2id cost {} is out of range", value));
+    let params = argon2::Params::new(cost(memory)?, cost(iterations)?, cost(parallelism)?, Some(len))
+        .map_err(|e| anyhow::anyhow!("Invalid Argon2id parameters: {}", e))?;
+    let mut output = vec![0u8; len];
+    argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password_into(password, salt, &mut output)
+        .map_err(|e| anyhow::anyhow!("Argon2id failed: {}", e))?;
+    Ok(output)
+}
+
+// The user's salt:<value>, Argon2 needs at least 8 bytes of it
+fn argon2id_salt(config: &AppConfig) -> Result<&[u8]> {
+    match &config.salt {
+        Some(salt) if salt.len() >= 8 => Ok(salt.as_bytes()),
+        _ => Err(anyhow::anyhow!("Argon2id needs a salt of at least 8 bytes, pass --salt or salt:<value> in --hash")),
+    }
+}
+
+// Argon2id digest of `data` with the configured costs and salt
+fn argon2id_digest(data: &[u8], config: &AppConfig) -> Result<Vec<u8>> {
+    let salt = argon2id_salt(config)?;
+    argon2id(data, salt, config.argon2_memory, config.argon2_iterations, config.argon2_parallelism, 32)
+}
+
+/// Argon2id PHC string (`$argon2id$v=19$m=..,t=..,p=..$salt$hash`) for `text`
+pub fn argon2id_phc(text: &str, config: &AppConfig) -> Result<String> {
+    let unpadded = |bytes: &[u8]| base64_encode(bytes).trim_end_matches('=').to_string();
+    Ok(format!("$argon2id$v=19$m={},t={},p={}${}${}",
+        config.argon2_memory, config.argon2_iterations, config.argon2_parallelism,
+        unpadded(argon2id_salt(config)?), unpadded(&argon2id_digest(text.as_bytes(), config)?)))
+}
+
+// Feed a reader to `update` in fixed-size chunks, returning the number of bytes read
+#[cfg(not(feature = "verify-only"))]
+fn stream_chunks<R: Read>(mut reader: R, mut update: impl FnMut(&[u8])) -> std::io::Result<u64> {
+    let mut buffer = [0u8; 64 * 1024];
+    let mut total = 0;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(total);
+        }
+        update(&buffer[..read]);
+        total += read as u64;
+    }
+}
+
+/// Digest a file without loading it into memory, returning the digest and the byte count.
+/// Argon2id takes its costs and salt from `config`.
+#[cfg(not(feature = "verify-only"))]
+pub fn get_file_digest(loc: &str, hash_name: &str, config: &AppConfig) -> Result<(Vec<u8>, u64)> {
+    let mut builder = HashBuilder::with_config(&AppConfig { method: hash_name.to_string(), ..config.clone() })?;
+    let total = stream_chunks(fs::File::open(loc)?, |chunk| builder.update(chunk))?;
+    Ok((builder.digest()?, total))
+}
+
+/// Hex digest of a file plus warnings; an empty file is still hashed but warned about,
+/// and is an error under strict mode
+#[cfg(not(feature = "verify-only"))]
+pub fn hash_file(loc: &str, hash_name: &str, config: &AppConfig, strict: bool) -> Result<(String, Vec<String>)> {
+    let (digest, total) = get_file_digest(loc, hash_name, config)?;
+    
+    let mut warnings = Vec::new();
+    if total == 0 {
+        warnings.push(format!("{} is empty, this is the digest of empty input", loc));
+    }
+    if strict {
+        if let Some(warning) = warnings.first() {
+            return Err(anyhow::anyhow!("{} (strict mode)", warning));
+        }
+    }
+    
+    Ok((hex_encode(&digest), warnings))
+}
+
+// Calculate string hash value
+pub fn get_string_hash(string: &str, hash_name: &str) -> Result<String> {
+    Ok(hex_encode(&get_string_digest(string, hash_name)?))
+}
+
+// Encode bytes as lowercase hex
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decode a hex string, None unless it is an even number of hex digits
+pub fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..text.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Encode bytes as standard padded base64
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = match chunk.len() {
+            1 => (chunk[0] as u32) << 16,
+            2 => (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8,
+            _ => (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8 | chunk[2] as u32,
+        };
+        
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// z-base-32 alphabet, ordered so the easiest characters to read aloud come up most
+const ZBASE32_ALPHABET: &[u8; 32] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+// Encode bytes as unpadded z-base-32, 5 bits per character
+pub fn zbase32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ZBASE32_ALPHABET[(buffer >> bits & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ZBASE32_ALPHABET[(buffer << (5 - bits) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+// Encode bytes as base58 (Bitcoin alphabet), each leading zero byte becomes '1'
+pub fn base58_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    
+    // Little-endian base58 digits of the big-endian input
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    std::iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&digit| ALPHABET[digit as usize] as char))
+        .collect()
+}
+
+/// Curated single-codepoint emoji, 64 entries so each byte maps without bias
+const EMOJI_SET: [char; 64] = [
+    '🐶', '🐱', '🐭', '🐹', '🐰', '🦊', '🐻', '🐼', '🐨', '🐯', '🦁', '🐮', '🐷', '🐸', '🐵', '🐔',
+    '🐧', '🐦', '🐤', '🦆', '🦅', '🦉', '🦇', '🐺', '🐗', '🐴', '🦄', '🐝', '🐛', '🦋', '🐌', '🐞',
+    '🐜', '🦗', '🐢', '🐍', '🦎', '🦖', '🦕', '🐙', '🦑', '🦐', '🦀', '🐡', '🐠', '🐟', '🐬', '🐳',
+    '🐋', '🦈', '🐊', '🐅', '🐆', '🦓', '🦍', '🐘', '🦏', '🐪', '🐫', '🦒', '🦘', '🐃', '🐂', '🐄',
+];
+
+// Encode bytes as one emoji per byte
+fn emoji_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| EMOJI_SET[(*byte % 64) as usize]).collect()
+}
+
+// Encode digest bytes with the configured output encoding
+pub fn encode_digest(bytes: &[u8], encoding: &str) -> Result<String> {
+    match encoding {
+        "hex" => Ok(hex_encode(bytes)),
+        "base64" => Ok(base64_encode(bytes)),
+        "zbase32" => Ok(zbase32_encode(bytes)),
+        "emoji" => Ok(emoji_encode(bytes)),
+        _ => Err(anyhow::anyhow!("Unsupported output encoding: {}, expected one of: {}", encoding, OUTPUT_ENCODINGS.join(", "))),
+    }
+}
+
+/// Output encodings `encode_digest` supports
+pub const OUTPUT_ENCODINGS: [&str; 4] = ["hex", "base64", "zbase32", "emoji"];
+
+/// Extra encodings accepted by --also-encode
+pub const ALSO_ENCODINGS: [&str; 3] = ["hex", "base64", "base58"];
+
+/// Encode the raw digest once per --also-encode entry, as (encoding, value) pairs
+pub fn also_encode(config: &AppConfig, spec: &str) -> Result<Vec<(String, String)>> {
+    let digest = get_method_digest(&build_base_text(config)?, &config.method, config)?;
+    spec.split(',')
+        .map(|encoding| encoding.trim())
+        .filter(|encoding| !encoding.is_empty())
+        .map(|encoding| {
+            let value = match encoding {
+                "hex" => hex_encode(&digest),
+                "base64" => base64_encode(&digest),
+                "base58" => base58_encode(&digest),
+                _ => return Err(anyhow::anyhow!("Unknown --also-encode encoding '{}', expected one of: {}", encoding, ALSO_ENCODINGS.join(", "))),
+            };
+            Ok((encoding.to_string(), value))
+        })
+        .collect()
+}
+
+// Check if any element in value is in check_list
+pub fn oneof(value: &str, check_list: &[&str]) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    
+    let items: Vec<&str> = value.split(',').map(|item| item.trim()).collect();
+    
+    for item in items {
+        if check_list.contains(&item) {
+            return true;
+        }
+    }
+    false
+}
+
+// Check if all elements in value are in check_list
+pub fn everyof(value: &str, check_list: &[&str]) -> bool {
+    if value.is_empty() {
+        return false;
+    }
+    
+    let items: Vec<&str> = value.split(',').map(|item| item.trim()).collect();
+    
+    for item in items {
+        if !check_list.contains(&item) {
+            return false;
+        }
+    }
+    true
+}
+
+// Check if string is empty or contains only whitespace
+pub fn string_is_empty(value: &str) -> bool {
+    value.trim().is_empty()
+}
+
+// Return default value if string is empty
+pub fn string_get(value: &str, default_value: &str) -> String {
+    if string_is_empty(value) {
+        default_value.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// Remove empty lines from multi-line text
+pub fn mlt_del_emptyline(lines: &[String]) -> Vec<String> {
+    lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .cloned()
+        .collect()
+}
+
+// Load multi-line text from file, return default text if file doesn't exist
+pub fn mlt_load_file(loc: &str, default_text: &str) -> Vec<String> {
+    if is_stream(loc) || Path::new(loc).exists() {
+        match read_text_file(loc, false) {
+            Ok(text) => text.lines().map(|s| s.to_string()).collect(),
+            Err(_) => vec![],
+        }
+    } else {
+        if default_text.is_empty() {
+            vec![]
+        } else {
+            default_text.lines().map(|s| s.to_string()).collect()
+        }
+    }
+}
+
+/// Path that stands for stdin, following the Unix `-` convention
+pub const STDIN_PATH: &str = "-";
+
+// Stdin and named pipes can only be read once, so the first read (until EOF) is kept
+// for later loads
+static STREAM_BYTES: std::sync::Mutex<std::collections::BTreeMap<String, Vec<u8>>> =
+    std::sync::Mutex::new(std::collections::BTreeMap::new());
+
+/// Whether the path is a one-shot stream (stdin or a named pipe) rather than a regular file
+pub fn is_stream(loc: &str) -> bool {
+    if loc == STDIN_PATH {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if let Ok(metadata) = fs::metadata(loc) {
+            return metadata.file_type().is_fifo();
+        }
+    }
+    false
+}
+
+fn read_stream_bytes(loc: &str) -> std::io::Result<Vec<u8>> {
+    let mut streams = STREAM_BYTES.lock().unwrap();
+    if let Some(bytes) = streams.get(loc) {
+        return Ok(bytes.clone());
+    }
+    
+    let mut bytes = Vec::new();
+    if loc == STDIN_PATH {
+        std::io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        fs::File::open(loc)?.read_to_end(&mut bytes)?;
+    }
+    streams.insert(loc.to_string(), bytes.clone());
+    Ok(bytes)
+}
+
+// Read file text, invalid UTF-8 is replaced lossily unless strict
+pub fn read_text_file(loc: &str, strict: bool) -> Result<String> {
+    let bytes = if is_stream(loc) {
+        read_stream_bytes(loc)?
+    } else {
+        let mut bytes = Vec::new();
+        fs::File::open(loc)?.read_to_end(&mut bytes)?;
+        bytes
+    };
+    decode_text(bytes, loc, strict)
+}
+
+// Decode file bytes by their BOM: UTF-8 BOMs are dropped so they never reach the first
+// field, UTF-16 is decoded, anything else is taken as UTF-8
+fn decode_text(bytes: Vec<u8>, loc: &str, strict: bool) -> Result<String> {
+    let utf16 = match bytes.as_slice() {
+        [0xef, 0xbb, 0xbf, rest @ ..] => return decode_text(rest.to_vec(), loc, strict),
+        [0xff, 0xfe, rest @ ..] => Some((rest, u16::from_le_bytes as fn([u8; 2]) -> u16)),
+        [0xfe, 0xff, rest @ ..] => Some((rest, u16::from_be_bytes as fn([u8; 2]) -> u16)),
+        _ => None,
+    };
+    if let Some((rest, from_bytes)) = utf16 {
+        if rest.len() % 2 != 0 {
+            return Err(anyhow::anyhow!("Invalid UTF-16 in '{}': odd number of bytes", loc));
+        }
+        let units: Vec<u16> = rest.chunks(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        return if strict {
+            String::from_utf16(&units).map_err(|_| anyhow::anyhow!("Invalid UTF-16 in '{}'", loc))
+        } else {
+            Ok(String::from_utf16_lossy(&units))
+        };
+    }
+    
+    if !strict {
+        return Ok(String::from_utf8_lossy(&bytes).into_owned());
+    }
+    
+    String::from_utf8(bytes).map_err(|e| {
+        anyhow::anyhow!("Invalid UTF-8 in '{}' at byte offset {}", loc, e.utf8_error().valid_up_to())
+    })
+}
+
+// Get last line of multi-line text
+pub fn mlt_get_lastline(lines: &[String]) -> String {
+    lines.last().cloned().unwrap_or_default()
+}
+
+// Get value for specified key from key-value string
+pub fn slkv_get(value: &str, slkv: &str, case_sensitive: bool) -> String {
+    if slkv.is_empty() {
+        return String::new();
+    }
+    
+    let pairs: Vec<&str> = slkv.split(',').map(|pair| pair.trim()).collect();
+    let search_key = if case_sensitive {
+        value.trim().to_string()
+    } else {
+        value.trim().to_uppercase()
+    };
+    
+    for pair in pairs {
+        if pair.contains(':') {
+            let parts: Vec<&str> = pair.splitn(2, ':').collect();
+            let key = parts[0].trim();
+            let val = parts[1].trim();
+            
+            let compare_key = if case_sensitive {
+                key.to_string()
+            } else {
+                key.to_uppercase()
+            };
+            
+            if compare_key == search_key {
+                return val.to_string();
+            }
+        }
+    }
+    
+    String::new()
+}
+
+/// Malformed key:value token, index is 1-based
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenError {
+    pub index: usize,
+    pub token: String,
+    pub reason: String,
+}
+
+impl TokenError {
+    pub fn new(index: usize, token: &str, reason: &str) -> Self {
+        Self { index, token: token.to_string(), reason: reason.to_string() }
+    }
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "token {} '{}': {}", self.index, self.token, self.reason)
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+// Config field names set by a key-value or head;tail string, unknown keys skipped
+pub fn token_fields(slkv: &str) -> Vec<&'static str> {
+    slkv.split([',', ';'])
+        .filter_map(|pair| pair.split_once(':'))
+        .filter_map(|(key, _)| match key.trim() {
+            "name" => Some("name"),
+            "email" => Some("email"),
+            "site" => Some("site"),
+            "method" => Some("method"),
+            "cut" => Some("cut_length"),
+            "end" => Some("end_char"),
+            "upper-start" => Some("upper_start"),
+            "encoding" => Some("output_encoding"),
+            "memory" => Some("argon2_memory"),
+            "iterations" => Some("argon2_iterations"),
+            "parallelism" => Some("argon2_parallelism"),
+            "salt" => Some("salt"),
+            _ => None,
+        })
+        .collect()
+}
+
+// Split a key-value string into (index, key, value) tokens, skipping empty ones
+pub fn slkv_tokens(slkv: &str) -> Result<Vec<(usize, &str, &str)>, TokenError> {
+    let mut tokens = Vec::new();
+    
+    for (index, pair) in slkv.split(',').enumerate() {
+        if pair.trim().is_empty() {
+            continue;
+        }
+        
+        let Some((key, value)) = pair.split_once(':') else {
+            return Err(TokenError::new(index + 1, pair.trim(), "expected key:value"));
+        };
+        if key.trim().is_empty() {
+            return Err(TokenError::new(index + 1, pair.trim(), "missing key"));
+        }
+        tokens.push((index + 1, key.trim(), value.trim()));
+    }
+    
+    Ok(tokens)
+}
+
+// Get part before semicolon
+pub fn sslf_get_head(data: &str) -> String {
+    data.split(';').next().unwrap_or(data).trim().to_string()
+}
+
+// Get part after semicolon
+pub fn sslf_get_tail(data: &str) -> String {
+    data.split(';').nth(1).unwrap_or("").trim().to_string()
+}
+
+/// First line of a file that isn't empty or a `#` comment, surrounding whitespace trimmed
+pub fn read_field_file(loc: &str, strict: bool) -> Result<String> {
+    read_text_file(loc, strict)?
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .ok_or_else(|| anyhow::anyhow!("{} has no value line", loc))
+}
+
+/// Parse a `head;tail` line into a validated configuration, a missing tail keeps the
+/// default hash settings
+pub fn parse_sslf(line: &str) -> Result<AppConfig> {
+    let mut config = AppConfig::default();
+    AppConfig::apply_sslf_config(&mut config, line.trim())?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Split a --comment-prefixes value into the extra markers passed to sslf_load_file
+pub fn parse_comment_prefixes(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Strip a line comment: whole lines starting with a prefix, or inline after whitespace
+/// (so `;` between head and tail and `//` in URLs are kept)
+pub fn strip_line_comment<'a>(line: &'a str, prefixes: &[String]) -> &'a str {
+    if prefixes.iter().any(|p| line.trim_start().starts_with(p.as_str())) {
+        return "";
+    }
+    let cut = prefixes.iter()
+        .filter_map(|p| {
+            line.char_indices()
+                .filter(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| i + c.len_utf8())
+                .find(|&i| line[i..].starts_with(p.as_str()))
+        })
+        .min();
+    match cut {
+        Some(i) => line[..i].trim_end(),
+        None => line,
+    }
+}
+
+// Load file and clean comments and empty lines, comment_prefixes are the user's extra markers
+pub fn sslf_load_file(loc: &str, default_text: &str, comment_prefixes: &[String]) -> Vec<String> {
+    let lines = mlt_load_file(loc, default_text);
+    
+    // Compile regex patterns
+    let comment_re = Regex::new(r"^#.*").unwrap();
+    let html_comment_re = Regex::new(r"<!--.*-->").unwrap();
+    
+    lines.iter()
+        .filter_map(|line| {
+            // Remove comment lines and HTML comments
+            let clean_line = comment_re.replace(line, "");
+            let clean_line = html_comment_re.replace(&clean_line, "");
+            let clean_line = strip_line_comment(&clean_line, comment_prefixes);
+            
+            if clean_line.trim().is_empty() {
+                None
+            } else {
+                Some(clean_line.to_string())
+            }
+        })
+        .collect()
+}
+
+// Parse a start:end line range (1-based, inclusive)
+pub fn parse_line_range(spec: &str) -> Result<(usize, usize)> {
+    let (start, end) = spec.split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid line range '{}', expected start:end", spec))?;
+    let start: usize = start.trim().parse()
+        .map_err(|_| anyhow::anyhow!("Invalid line range start '{}'", start))?;
+    let end: usize = end.trim().parse()
+        .map_err(|_| anyhow::anyhow!("Invalid line range end '{}'", end))?;
+    
+    if start == 0 {
+        return Err(anyhow::anyhow!("Line range starts at 1"));
+    }
+    if start > end {
+        return Err(anyhow::anyhow!("Line range {}:{} is inverted", start, end));
+    }
+    
+    Ok((start, end))
+}
+
+// Select lines start:end (1-based, inclusive) from cleaned lines
+pub fn select_line_range(lines: &[String], line_range: (usize, usize)) -> Result<Vec<String>> {
+    let (start, end) = line_range;
+    if end > lines.len() {
+        return Err(anyhow::anyhow!("Line range {}:{} exceeds {} available lines", start, end, lines.len()));
+    }
+    
+    Ok(lines[start - 1..end].to_vec())
+}
+
+// Remove name:, email:, site: prefixes from data
+pub fn shtkv_get_pure_v(data: &str) -> String {
+    if data.is_empty() {
+        return String::new();
+    }
+    
+    // Compile regex patterns
+    let prefix_re = Regex::new(r"(name:|email:|site:)").unwrap();
+    let trailing_comma_re = Regex::new(r",$").unwrap();
+    
+    let cleaned = prefix_re.replace_all(data, "");
+    let cleaned = trailing_comma_re.replace(&cleaned, "");
+    cleaned.trim().to_string()
+}
+
+// Get filename from path
+// Both separators are handled on every platform, not just the OS one
+pub fn path_get_name(path: &str) -> String {
+    let path = path.trim_end_matches(PATH_SEPARATORS);
+    path.rsplit(PATH_SEPARATORS)
+        .next()
+        .filter(|name| !["", ".", ".."].contains(name))
+        .unwrap_or("")
+        .to_string()
+}
+
+// Get directory part from path
+pub fn path_get_dirs(path: &str) -> String {
+    let path = path.trim_end_matches(PATH_SEPARATORS);
+    match path.rfind(PATH_SEPARATORS) {
+        Some(index) => {
+            let dirs = path[..index].trim_end_matches(PATH_SEPARATORS);
+            // Keep a root separator rather than returning an empty parent
+            if dirs.is_empty() { path[..1].to_string() } else { dirs.to_string() }
+        }
+        None => "".to_string(),
+    }
+}
+
+/// Path separators recognised by the path helpers
+const PATH_SEPARATORS: [char; 2] = ['/', '\\'];
+
+// Normalize path separators
+pub fn path_normalize(path: &str, search: &str, replace: &str) -> String {
+    path.replace(search, replace)
+}
+
+// Check if path exists
+pub fn os_path_exist(loc: &str) -> bool {
+    Path::new(loc).exists()
+}
+
+// Create directory
+pub fn os_path_make(loc: &str) -> Result<(), std::io::Error> {
+    if !loc.is_empty() && !os_path_exist(loc) {
+        fs::create_dir_all(loc)
+    } else {
+        Ok(())
+    }
+}
+
+// Add password to file
+pub fn add_password_to_file(loc: &str, password: &str) -> Result<(), std::io::Error> {
+    let existing = if os_path_exist(loc) { Some(fs::read_to_string(loc)?) } else { None };
+    fs::write(loc, appended_content(existing.as_deref(), password))
+}
+
+// File content after appending a password entry
+fn appended_content(existing: Option<&str>, password: &str) -> String {
+    match existing {
+        Some(content) => {
+            let mut lines: Vec<&str> = content.lines().collect();
+            lines.push(password);
+            lines.join("\n")
+        }
+        None => password.to_string(),
+    }
+}
+
+/// Save a result to every output file, each reported separately so one failure doesn't stop the rest
+#[cfg(not(feature = "verify-only"))]
+pub fn save_result(config: &AppConfig, result: &str) -> Vec<(String, Result<(), std::io::Error>)> {
+    let time = config.save_time.then(get_time_now);
+    let entry = saved_entry_line(result, time.as_deref(), config.counter);
+    
+    if config.atomic_multi_save {
+        return save_result_atomic(config, &entry);
+    }
+    
+    config.output_files.iter()
+        .map(|save_path| {
+            if skip_existing_entry(config, save_path) {
+                return (save_path.clone(), Ok(()));
+            }
+            
+            let outcome = resolve_save_target(save_path, config.follow_symlinks)
+                .and_then(|target| {
+                    add_password_to_file(&target, &save_text(config, save_path, &entry))?;
+                    trim_after_save(config, &target, save_path)
+                });
+            (save_path.clone(), outcome)
+        })
+        .collect()
+}
+
+// Text appended to a save file: the entry, after the input file content unless saving into it
+#[cfg(not(feature = "verify-only"))]
+fn save_text(config: &AppConfig, save_path: &str, entry: &str) -> String {
+    if config.input_file.as_deref() == Some(save_path) {
+        entry.to_string()
+    } else {
+        format!("{}\n{}",
+               config.input_file.as_ref()
+                   .and_then(|path| read_text_file(path, false).ok())
+                   .unwrap_or_default(),
+               entry)
+    }
+}
+
+// With --only-if-absent, skip targets that already hold this name and site
+#[cfg(not(feature = "verify-only"))]
+fn skip_existing_entry(config: &AppConfig, save_path: &str) -> bool {
+    if config.only_if_absent && count_saved_entries(save_path, &config.name, &config.site) > 0 {
+        info_status(&format!("{} - {} already has an entry for {},{}, skipped",
+                             get_time_now(), save_path, config.name, config.site), 2);
+        return true;
+    }
+    false
+}
+
+// Archive old entries once a save has landed
+#[cfg(not(feature = "verify-only"))]
+fn trim_after_save(config: &AppConfig, target: &str, save_path: &str) -> Result<(), std::io::Error> {
+    if let Some(max_entries) = config.max_entries {
+        let archived = trim_save_file(target, max_entries)?;
+        if archived > 0 {
+            info_status(&format!("{} - Archived {} old entries from {}", get_time_now(), archived, save_path), 3);
+        }
+    }
+    Ok(())
+}
+
+/// One save target staged next to its file: (save path, resolved target, temp file, original content)
+#[cfg(not(feature = "verify-only"))]
+type StagedSave = (String, String, String, Option<String>);
+
+/// All-or-nothing save: every target is written to a temp file first and the temps are
+/// renamed into place only if all writes succeeded, otherwise nothing is modified
+#[cfg(not(feature = "verify-only"))]
+fn save_result_atomic(config: &AppConfig, entry: &str) -> Vec<(String, Result<(), std::io::Error>)> {
+    let mut staged: Vec<StagedSave> = Vec::new();
+    
+    // Stage every target
+    for save_path in &config.output_files {
+        if skip_existing_entry(config, save_path) {
+            continue;
+        }
+        let outcome = resolve_save_target(save_path, config.follow_symlinks)
+            .and_then(|target| {
+                let original = if os_path_exist(&target) { Some(fs::read_to_string(&target)?) } else { None };
+                let temp = format!("{}.tmp-{}", target, std::process::id());
+                fs::write(&temp, appended_content(original.as_deref(), &save_text(config, save_path, entry)))?;
+                Ok((save_path.clone(), target, temp, original))
+            });
+        match outcome {
+            Ok(stage) => staged.push(stage),
+            Err(e) => {
+                for (_, _, temp, _) in &staged {
+                    let _ = fs::remove_file(temp);
+                }
+                return rolled_back_outcomes(config, save_path, e);
+            }
+        }
+    }
+    
+    // Commit, restoring already renamed targets if a rename fails
+    for (index, (save_path, target, temp, _)) in staged.iter().enumerate() {
+        if let Err(e) = fs::rename(temp, target) {
+            for (_, target, _, original) in &staged[..index] {
+                let _ = match original {
+                    Some(content) => fs::write(target, content),
+                    None => fs::remove_file(target),
+                };
+            }
+            for (_, _, temp, _) in &staged[index..] {
+                let _ = fs::remove_file(temp);
+            }
+            return rolled_back_outcomes(config, save_path, e);
+        }
+    }
+    
+    config.output_files.iter()
+        .map(|save_path| {
+            let outcome = match staged.iter().find(|(path, ..)| path == save_path) {
+                Some((_, target, _, _)) => trim_after_save(config, target, save_path),
+                None => Ok(()),
+            };
+            (save_path.clone(), outcome)
+        })
+        .collect()
+}
+
+// The failed target keeps its error, every other target reports the rollback
+#[cfg(not(feature = "verify-only"))]
+fn rolled_back_outcomes(config: &AppConfig, failed: &str, error: std::io::Error) -> Vec<(String, Result<(), std::io::Error>)> {
+    let mut error = Some(error);
+    config.output_files.iter()
+        .map(|save_path| {
+            let outcome = match error.take_if(|_| save_path == failed) {
+                Some(error) => error,
+                None => std::io::Error::other(format!("rolled back because {} failed", failed)),
+            };
+            (save_path.clone(), Err(outcome))
+        })
+        .collect()
+}
+
+/// Smoke test of the core paths inside a fresh temp directory, removed afterwards
+#[cfg(not(feature = "verify-only"))]
+pub fn health_check() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("pass-craft-health-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let outcome = health_check_in(&dir);
+    fs::remove_dir_all(&dir)?;
+    outcome
+}
+
+// Each step reports via info_status and stops at the first failure
+#[cfg(not(feature = "verify-only"))]
+fn health_check_in(dir: &Path) -> Result<()> {
+    let config_path = dir.join("config.txt");
+    let save_path = dir.join("passwords.md");
+    let path_str = |path: &Path| path.to_string_lossy().to_string();
+    
+    fs::write(&config_path, "# health check\nname:health,email:health@example.com,site:example.com;method:sha256,cut:12,end:!,upper-start:4\n")?;
+    let mut config = AppConfig::load_from_file(&path_str(&config_path), None, &[])?;
+    if config.name != "health" || config.cut_length != 12 {
+        return Err(anyhow::anyhow!("Parse: sample config loaded as {},{}", config.name, config.cut_length));
+    }
+    info_status("Health check: parse config ok", 0);
+    
+    let result = generate(&config)?;
+    info_status("Health check: generate ok", 0);
+    
+    config.validate()?;
+    info_status("Health check: validate ok", 0);
+    
+    config.output_files = vec![path_str(&save_path)];
+    for (path, outcome) in save_result(&config, &result.to_string()) {
+        outcome.map_err(|e| anyhow::anyhow!("Save to {} failed: {}", path, e))?;
+    }
+    info_status("Health check: save ok", 0);
+    
+    let reloaded = read_text_file(&path_str(&save_path), false)?
+        .lines()
+        .filter_map(parse_saved_entry)
+        .next_back()
+        .ok_or_else(|| anyhow::anyhow!("Reload: no saved entry in {}", save_path.display()))?;
+    if reloaded.password != result.password {
+        return Err(anyhow::anyhow!("Reload: saved password {} differs from generated {}", reloaded.password, result.password));
+    }
+    info_status("Health check: reload ok", 0);
+    Ok(())
+}
+
+/// Keep the newest `max_entries` saved entries, moving older ones (by file order) to
+/// `<loc>.archive`. Config and other lines stay put. Returns the number archived.
+pub fn trim_save_file(loc: &str, max_entries: usize) -> Result<usize, std::io::Error> {
+    let content = fs::read_to_string(loc)?;
+    let entries = content.lines().filter(|line| parse_saved_entry(line).is_some()).count();
+    if entries <= max_entries {
+        return Ok(0);
+    }
+    
+    let mut excess = entries - max_entries;
+    let (mut kept, mut archived) = (Vec::new(), Vec::new());
+    for line in content.lines() {
+        if excess > 0 && parse_saved_entry(line).is_some() {
+            archived.push(line);
+            excess -= 1;
+        } else {
+            kept.push(line);
+        }
+    }
+    
+    add_password_to_file(&format!("{}.archive", loc), &archived.join("\n"))?;
+    fs::write(loc, kept.join("\n"))?;
+    Ok(archived.len())
+}
+
+/// Remove entries archived by `trim_save_file` (in `<loc>.archive`) that were saved more
+/// than `days` before `now`. Undated entries are kept and the live save file is never
+/// touched. Returns the number pruned.
+#[cfg(not(feature = "verify-only"))]
+pub fn prune_archived(loc: &str, days: u32, now: &str) -> Result<usize> {
+    let parse = |time: &str| chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S");
+    let cutoff = parse(now)? - chrono::Duration::days(days as i64);
+    let archive = format!("{}.archive", loc);
+    let content = read_text_file(&archive, false)?;
+    
+    let (kept, pruned): (Vec<&str>, Vec<&str>) = content.lines().partition(|line| {
+        let saved = parse_saved_entry(line)
+            .and_then(|entry| entry.time)
+            .and_then(|time| parse(&time).ok());
+        saved.is_none_or(|saved| saved >= cutoff)
+    });
+    if !pruned.is_empty() {
+        fs::write(&archive, kept.join("\n"))?;
+    }
+    Ok(pruned.len())
+}
+
+/// Real file behind a save path. Symlinks are followed by default so the link itself
+/// survives the rewrite; with `follow` off a symlinked target is refused.
+pub fn resolve_save_target(loc: &str, follow: bool) -> Result<String, std::io::Error> {
+    let Ok(metadata) = fs::symlink_metadata(loc) else {
+        return Ok(loc.to_string());
+    };
+    if !metadata.file_type().is_symlink() {
+        return Ok(loc.to_string());
+    }
+    if !follow {
+        return Err(std::io::Error::other(format!("{} is a symlink (--no-follow-symlinks)", loc)));
+    }
+    
+    let link = fs::read_link(loc)?;
+    let target = match Path::new(loc).parent() {
+        Some(parent) if link.is_relative() => parent.join(link),
+        _ => link,
+    };
+    debug!("Save path {} resolved to {}", loc, target.display());
+    Ok(target.to_string_lossy().to_string())
+}
+
+// Wrap text with HTML comments
+pub fn html_comment_wrap(text: &str) -> String {
+    format!("<!-- {} -->", text)
+}
+
+// Remove HTML comment wrapping
+pub fn html_comment_unwrap(text: &str) -> String {
+    text.trim()
+        .trim_start_matches("<!--")
+        .trim_end_matches("-->")
+        .trim()
+        .to_string()
+}
+
+/// Saved result entry (`<!-- name,password,site -->`, optionally `;time:...` metadata)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedEntry {
+    pub name: String,
+    pub password: String,
+    pub site: String,
+    pub time: Option<String>,
+    pub version: Option<String>,
+    pub counter: Option<usize>,
+}
+
+/// Save file line for a result, recording the pass-craft version (and the time and rotation
+/// counter when given) as metadata so entries from a version that generates differently can
+/// be spotted and --auto-counter never reuses a counter
+pub fn saved_entry_line(result: &str, time: Option<&str>, counter: Option<usize>) -> String {
+    let mut metadata = Vec::new();
+    if let Some(time) = time {
+        metadata.push(format!("time:{}", time));
+    }
+    if let Some(counter) = counter {
+        metadata.push(format!("counter:{}", counter));
+    }
+    metadata.push(format!("version:{}", env!("CARGO_PKG_VERSION")));
+    html_comment_wrap(&format!("{};{}", result, metadata.join(",")))
+}
+
+/// Metadata keys saved_entry_line appends after the result
+const SAVED_ENTRY_METADATA: [&str; 3] = ["time", "counter", "version"];
+
+// Parse a saved result line, skipping plain and commented-out config lines
+pub fn parse_saved_entry(line: &str) -> Option<SavedEntry> {
+    let line = line.trim();
+    if !line.starts_with("<!--") || !line.ends_with("-->") {
+        return None;
+    }
+    
+    // Entries are written with trailing ;time:...,version:... metadata; without it, a line whose
+    // head is all key:value pairs is a commented-out config line rather than an older entry
+    let text = html_comment_unwrap(line);
+    let is_key = |key: &str| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let is_metadata = |tail: &str| tail.split(',')
+        .all(|pair| pair.split_once(':').is_some_and(|(key, _)| SAVED_ENTRY_METADATA.contains(&key.trim())));
+    let is_config = |text: &str| sslf_get_head(text).split(',')
+        .all(|pair| pair.split_once(':').is_some_and(|(key, _)| is_key(key.trim())));
+    let (text, metadata) = match text.rsplit_once(';') {
+        Some((head, tail)) if is_metadata(tail) => (head, tail),
+        _ if is_config(&text) => return None,
+        _ => (text.as_str(), ""),
+    };
+    let parts: Vec<&str> = text.split(',').map(|part| part.trim()).collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    
+    let time = slkv_get("time", metadata, true);
+    let version = slkv_get("version", metadata, true);
+    Some(SavedEntry {
+        name: parts[0].to_string(),
+        password: parts[1..parts.len() - 1].join(","),
+        site: parts[parts.len() - 1].to_string(),
+        time: (!time.is_empty()).then_some(time),
+        version: (!version.is_empty()).then_some(version),
+        counter: slkv_get("counter", metadata, true).parse().ok(),
+    })
+}
+
+/// Saved entries recorded by a different pass-craft version than `running`, which may not
+/// regenerate identically, one message per entry with its 1-based line number
+pub fn verify_file(loc: &str, running: &str) -> Result<Vec<String>> {
+    Ok(read_text_file(loc, false)?
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let entry = parse_saved_entry(line)?;
+            let version = entry.version.filter(|version| version != running)?;
+            Some(format!("Line {}: {},{} was saved by pass-craft {}, running {}",
+                         index + 1, entry.name, entry.site, version, running))
+        })
+        .collect())
+}
+
+/// Saved entry timestamps that are in the future relative to `now` or earlier than an
+/// entry above them, one message per problem with its 1-based line number
+#[cfg(not(feature = "verify-only"))]
+pub fn check_clock(loc: &str, now: &str) -> Result<Vec<String>> {
+    let parse = |time: &str| chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S");
+    let now = parse(now)?;
+    let mut issues = Vec::new();
+    let mut latest: Option<chrono::NaiveDateTime> = None;
+    
+    for (index, line) in read_text_file(loc, false)?.lines().enumerate() {
+        let Some(time) = parse_saved_entry(line).and_then(|entry| entry.time) else {
+            continue;
+        };
+        let Ok(saved) = parse(&time) else {
+            issues.push(format!("Line {}: unreadable time '{}'", index + 1, time));
+            continue;
+        };
+        
+        if saved > now {
+            issues.push(format!("Line {}: {} is in the future", index + 1, time));
+        }
+        if latest.is_some_and(|latest| saved < latest) {
+            issues.push(format!("Line {}: {} is earlier than a previous entry", index + 1, time));
+        }
+        latest = latest.max(Some(saved));
+    }
+    
+    Ok(issues)
+}
+
+// Get the most recently saved password for name,site
+pub fn latest_saved_password(loc: &str, name: &str, site: &str) -> Option<String> {
+    mlt_load_file(loc, "")
+        .iter()
+        .rev()
+        .filter_map(|line| parse_saved_entry(line))
+        .find(|entry| entry.name == name && entry.site == site)
+        .map(|entry| entry.password)
+}
+
+/// Share of saved result lines above which a file looks like a save file
+const SAVE_FILE_ENTRY_RATIO: f64 = 0.8;
+
+// Check if lines are dominated by saved result entries rather than config
+pub fn looks_like_save_file(lines: &[String]) -> bool {
+    let lines = mlt_del_emptyline(lines);
+    if lines.is_empty() {
+        return false;
+    }
+    
+    let entries = lines.iter().filter(|line| parse_saved_entry(line).is_some()).count();
+    entries as f64 / lines.len() as f64 >= SAVE_FILE_ENTRY_RATIO
+}
+
+// Warning for a config file that looks like a password save file
+pub fn save_file_warning(loc: &str) -> Option<String> {
+    if looks_like_save_file(&mlt_load_file(loc, "")) {
+        Some(format!("'{}' looks like a password save file, not a config file", loc))
+    } else {
+        None
+    }
+}
+
+/// Compare a new password with the live entry in every save file. Returns one message per
+/// file that already has an entry, and errors when one changed unless `yes` confirms it.
+pub fn diff_against_saved(config: &AppConfig, password: &str, yes: bool) -> Result<Vec<String>> {
+    let mut messages = Vec::new();
+    let mut changed = Vec::new();
+    for save_path in &config.output_files {
+        match latest_saved_password(save_path, &config.name, &config.site) {
+            Some(saved) if saved == password => messages.push(format!("{}: password unchanged", save_path)),
+            Some(_) => {
+                messages.push(format!("{}: password changed for {},{}", save_path, config.name, config.site));
+                changed.push(save_path.as_str());
+            }
+            None => {}
+        }
+    }
+    if !changed.is_empty() && !yes {
+        return Err(anyhow::anyhow!("Password for {},{} changed in {}, rerun with --yes to save it", config.name, config.site, changed.join(", ")));
+    }
+    Ok(messages)
+}
+
+// Count saved entries for name,site in the save file
+pub fn count_saved_entries(loc: &str, name: &str, site: &str) -> usize {
+    mlt_load_file(loc, "")
+        .iter()
+        .filter_map(|line| parse_saved_entry(line))
+        .filter(|entry| entry.name == name && entry.site == site)
+        .count()
+}
+
+/// Counter for the next --auto-counter rotation of name,site: one past the highest counter
+/// recorded in the save file or its archive, and never below the number of entries in both
+pub fn next_auto_counter(loc: &str, name: &str, site: &str) -> usize {
+    let archive = format!("{}.archive", loc);
+    let entries: Vec<SavedEntry> = [loc, archive.as_str()].iter()
+        .flat_map(|path| mlt_load_file(path, ""))
+        .filter_map(|line| parse_saved_entry(&line))
+        .filter(|entry| entry.name == name && entry.site == site)
+        .collect();
+    entries.iter()
+        .filter_map(|entry| entry.counter.map(|counter| counter + 1))
+        .max()
+        .unwrap_or(0)
+        .max(entries.len())
+}
+
+/// Break text into lines of at most `width` characters for display, 0 leaves it as is
+pub fn wrap_display(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(width)
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get current time in formatted string
+pub fn get_time_now() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Quiet mode keeps stdout for machine-readable output: steps and info are dropped,
+/// warnings and errors go to stderr
+static QUIET: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, std::sync::atomic::Ordering::Relaxed);
+}
+
+fn is_quiet() -> bool {
+    QUIET.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Display a step header with centered text
+pub fn info_step(msg: &str, length: usize, fillchar: char) {
+    if is_quiet() {
+        return;
+    }
+    
+    let msg_len = msg.chars().count();
+    if msg_len >= length {
+        println!("{}", msg);
+        return;
+    }
+    
+    let padding_len = (length - msg_len) / 2;
+    let padding = fillchar.to_string().repeat(padding_len);
+    
+    // Use format! for precise length control
+    let formatted = format!("{}{}{}", padding, msg, padding);
+    // Truncate to exact length (there might be 1 character difference for odd lengths)
+    println!("{}", &formatted[..length.min(formatted.len())]);
+}
+
+/// Display status message with appropriate icon
+pub fn info_status(msg_body: &str, status: u8) {
+    let icon = match status {
+        0 => "✅", // Success
+        1 => "❌", // Error
+        2 => "⚠️",  // Warning
+        _ => "ℹ️", // Info
+    };
+    if !is_quiet() {
+        println!("{} {}", icon, msg_body);
+    } else if status == 1 || status == 2 {
+        eprintln!("{} {}", icon, msg_body);
+    }
+}
+
+/// Build the text that gets hashed from the parsed fields, never the raw --text string.
+/// Components live in a fixed-order Vec (never a HashMap) so the hashed bytes are stable.
+pub fn build_base_text(config: &AppConfig) -> Result<String> {
+    Ok(match &config.domain {
+        Some(domain) => format!("{}\n{}", domain, build_field_text(config)?),
+        None => build_field_text(config)?,
+    })
+}
+
+// Base text without the domain tag
+fn build_field_text(config: &AppConfig) -> Result<String> {
+    if let Some(whole_file) = &config.whole_file {
+        return Ok(whole_file.clone());
+    }
+    
+    let site = if config.canonicalize_site { canonical_site(&config.site) } else { config.site.as_str() };
+    let lowercased;
+    let site = if config.normalize_site_case {
+        lowercased = lowercase_site_domain(site);
+        lowercased.as_str()
+    } else {
+        site
+    };
+    let mut components: Vec<&str> = config.fields.iter()
+        .map(|field| match field.as_str() {
+            "name" => config.name.as_str(),
+            "email" => config.email.as_str(),
+            "site" => site,
+            _ => "",
+        })
+        .collect();
+    
+    // Per-field methods: each identity field is replaced by its own hex digest
+    let field_digests: Vec<String> = if config.field_methods.is_empty() {
+        Vec::new()
+    } else {
+        config.fields.iter()
+            .zip(&components)
+            .map(|(field, value)| get_string_hash(value, config.field_method(field)))
+            .collect::<Result<_>>()?
+    };
+    if !field_digests.is_empty() {
+        components = field_digests.iter().map(String::as_str).collect();
+    }
+    
+    let counter = config.counter.map(|counter| counter.to_string());
+    if let Some(counter) = &counter {
+        components.push(counter);
+    }
+    if let Some(previous_password) = &config.previous_password {
+        components.push(previous_password);
+    }
+    if let Some(nonce) = &config.nonce {
+        components.push(nonce);
+    }
+    if let Some(salt) = &config.salt {
+        components.push(salt);
+    }
+    if config.dedup_fields {
+        let mut seen = Vec::new();
+        components.retain(|component| {
+            let first = !seen.contains(component);
+            seen.push(*component);
+            first
+        });
+    }
+    
+    // Byte length prefixes make the split points explicit: 4:john,3:a,b
+    if config.unambiguous_fields {
+        return Ok(components.iter()
+            .map(|component| format!("{}:{}", component.len(), component))
+            .collect::<Vec<_>>()
+            .join(","));
+    }
+    
+    Ok(components.join(","))
+}
+
+/// Parse --field-methods pairs, e.g. `name:sha256,site:sha1`
+pub fn parse_field_methods(spec: &str) -> Result<Vec<(String, String)>> {
+    spec.split(',')
+        .map(|pair| pair.trim())
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (field, method) = pair.split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Invalid field method '{}', expected field:method", pair))?;
+            Ok((field.trim().to_lowercase(), method.trim().to_uppercase()))
+        })
+        .collect()
+}
+
+/// 16 random bytes from the OS as hex, for deliberately non-reproducible passwords
+pub fn random_nonce() -> Result<String> {
+    if !cfg!(unix) {
+        return Err(anyhow::anyhow!("--random-nonce needs /dev/urandom, not available on this platform"));
+    }
+    
+    let mut bytes = [0u8; 16];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(hex_encode(&bytes))
+}
+
+/// Shell assignment `export VAR='value'`, single quotes in the value are closed, escaped and reopened
+pub fn env_assignment(var: &str, value: &str) -> Result<String> {
+    let valid = var.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid environment variable name: {}", var));
+    }
+    
+    Ok(format!("export {}='{}'", var, value.replace('\'', "'\\''")))
+}
+
+/// Collapse common URL forms of a site: `https://www.example.com/` becomes `example.com`
+pub fn canonical_site(site: &str) -> &str {
+    let site = site.trim();
+    let site = site.split_once("://").map_or(site, |(_, rest)| rest);
+    let site = site.strip_prefix("www.").unwrap_or(site);
+    site.trim_end_matches('/')
+}
+
+/// Lowercase the scheme and domain of a site, leaving any path after them as given:
+/// `HTTPS://Example.COM/Docs` becomes `https://example.com/Docs`
+pub fn lowercase_site_domain(site: &str) -> String {
+    let path_start = site.find("://").map_or(0, |scheme_end| scheme_end + 3);
+    let domain_end = site[path_start..].find('/').map_or(site.len(), |slash| path_start + slash);
+    format!("{}{}", site[..domain_end].to_lowercase(), &site[domain_end..])
+}
+
+/// Estimated strength of each stage of the derivation, in bits
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyBreakdown {
+    pub input_bits: f64,
+    pub digest_bits: f64,
+    pub charset_size: usize,
+    pub output_length: usize,
+    pub output_bits: f64,
+}
+
+impl EntropyBreakdown {
+    /// The password is derived, so it can be no stronger than its weakest stage
+    pub fn effective_bits(&self) -> f64 {
+        self.input_bits.min(self.digest_bits).min(self.output_bits)
+    }
+    
+    /// Human-readable report, one line per component plus caveats
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("Input fields: ~{:.0} bits (upper bound, real names and sites are far more guessable)", self.input_bits),
+            format!("Algorithm digest: {:.0} bits", self.digest_bits),
+            format!("Output length: {} characters", self.output_length),
+            format!("Charset size: {} symbols, {:.0} bits at this length", self.charset_size, self.output_bits),
+            format!("Effective: ~{:.0} bits, the weakest of the above", self.effective_bits()),
+            "Caveat: generation is deterministic, anyone who can guess the input fields gets the password, however long it looks".to_string(),
+        ]
+    }
+}
+
+// Brute-force estimate for one field: length times log2 of the character classes it uses
+fn text_entropy_bits(text: &str) -> f64 {
+    let classes = [
+        (text.chars().any(|c| c.is_ascii_lowercase()), 26),
+        (text.chars().any(|c| c.is_ascii_uppercase()), 26),
+        (text.chars().any(|c| c.is_ascii_digit()), 10),
+        (text.chars().any(|c| !c.is_ascii_alphanumeric()), 33),
+    ];
+    let pool: u32 = classes.iter().filter(|(used, _)| *used).map(|(_, size)| size).sum();
+    if pool == 0 {
+        return 0.0;
+    }
+    text.chars().count() as f64 * (pool as f64).log2()
+}
+
+/// Break down the password's strength into input, algorithm, length and charset
+pub fn explain_entropy(config: &AppConfig) -> EntropyBreakdown {
+    let config = &config.with_resolved_cut();
+    let fields = [("name", &config.name), ("email", &config.email), ("site", &config.site)];
+    let mut input_bits: f64 = fields.iter()
+        .filter(|(field, _)| config.fields.iter().any(|f| f == field))
+        .map(|(_, value)| text_entropy_bits(value))
+        .sum();
+    if let Some(nonce) = &config.nonce {
+        input_bits += nonce.len() as f64 * 4.0;
+    }
+    if let Some(salt) = &config.salt {
+        input_bits += text_entropy_bits(salt);
+    }
+    
+    let digest_bits = match find_algorithm(&config.method) {
+        Some(algorithm) if algorithm.xof => (xof_output_len(config) * 8) as f64,
+        Some(algorithm) => (algorithm.output_bytes * 8) as f64,
+        None => 0.0,
+    };
+    // Hex letters may be uppercased by the case transform, which adds a-f again
+    let charset_size = match config.output_encoding.as_str() {
+        "hex" if config.upper_start > 0 && config.pipeline.iter().any(|t| t == "case") => 22,
+        "hex" => 16,
+        "zbase32" => 32,
+        _ => 64,
+    };
+    let output_length = match (config.truncate_unit.as_str(), config.output_encoding.as_str()) {
+        ("bytes", "hex") => config.cut_length * 2,
+        ("bytes", "base64") => config.cut_length.div_ceil(3) * 4,
+        _ => config.cut_length,
+    };
+    // The end character is fixed, so it adds nothing
+    let random_chars = output_length.saturating_sub(usize::from(!config.end_char.is_empty()));
+    
+    EntropyBreakdown {
+        input_bits,
+        digest_bits,
+        charset_size,
+        output_length,
+        output_bits: random_chars as f64 * (charset_size as f64).log2(),
+    }
+}
+
+/// Stable fingerprint of every setting that affects the generated password.
+/// File paths and platform details are left out, the field order is fixed by `to_toml`.
+pub fn config_fingerprint(config: &AppConfig) -> String {
+    hex_encode(&Sha256::digest(config.to_toml().as_bytes()))
+}
+
+/// Raw hex digest of the base text, without truncation or formatting
+pub fn raw_digest(config: &AppConfig) -> Result<String> {
+    Ok(hex_encode(&get_method_digest(&build_base_text(config)?, &config.method, config)?))
+}
+
+/// Password for pre-hashed material: the supplied hex digest goes straight to formatting
+pub fn format_input_hash(hex: &str, config: &AppConfig) -> Result<String> {
+    let digest = hex_decode(hex.trim())
+        .filter(|digest| !digest.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Input hash must be an even number of hex digits: '{}'", hex))?;
+    let config = &config.with_resolved_cut();
+    if config.no_transforms {
+        return Ok(hex_encode(&digest));
+    }
+    format_digest(&digest, config)
+}
+
+/// Elapsed time per phase, in the order phases were first recorded
+#[derive(Debug, Default, Clone)]
+pub struct Timings {
+    pub phases: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Add elapsed time to a phase
+    pub fn record(&mut self, phase: &str, elapsed: Duration) {
+        match self.phases.iter_mut().find(|(name, _)| name == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => self.phases.push((phase.to_string(), elapsed)),
+        }
+    }
+    
+    /// Human readable breakdown, one phase per line
+    pub fn report(&self) -> Vec<String> {
+        self.phases.iter()
+            .map(|(name, elapsed)| format!("{:<10} {:.3} ms", name, elapsed.as_secs_f64() * 1000.0))
+            .collect()
+    }
+    
+    /// JSON object of phase name to milliseconds
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.phases.iter()
+            .map(|(name, elapsed)| format!("\"{}\": {:.3}", name, elapsed.as_secs_f64() * 1000.0))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+}
+
+/// Generated password with the identity it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationResult {
+    pub name: String,
+    pub site: String,
+    pub password: String,
+    pub method: String,
+    pub generated_at: String,
+}
+
+impl GenerationResult {
+    #[cfg(not(feature = "verify-only"))]
+    fn new(config: &AppConfig, password: String) -> Self {
+        Self {
+            name: config.name.clone(),
+            site: config.site.clone(),
+            password,
+            method: config.method.clone(),
+            generated_at: get_time_now(),
+        }
+    }
+}
+
+/// The `name,password,site` line that gets printed and saved
+impl std::fmt::Display for GenerationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.name, self.password, self.site)
+    }
+}
+
+/// Reported by `verify-only` builds, where generation and saving are compiled out
+#[cfg(feature = "verify-only")]
+pub const GENERATION_DISABLED: &str = "Password generation is disabled in this verify-only build";
+
+/// Generate password hash
+#[cfg(feature = "verify-only")]
+pub fn generate_password_hash(_config: &AppConfig) -> Result<String> {
+    Err(anyhow::anyhow!(GENERATION_DISABLED))
+}
+
+/// Generate password hash
+#[cfg(not(feature = "verify-only"))]
+pub fn generate_password_hash(config: &AppConfig) -> Result<String> {
+    generate_password_hash_timed(config, &mut Timings::default())
+}
+
+/// Generate password hash, recording hashing and encoding time
+#[cfg(not(feature = "verify-only"))]
+pub fn generate_password_hash_timed(config: &AppConfig, timings: &mut Timings) -> Result<String> {
+    generate_timed(config, timings).map(|result| result.to_string())
+}
+
+/// Generate the password as a structured result
+#[cfg(not(feature = "verify-only"))]
+pub fn generate(config: &AppConfig) -> Result<GenerationResult> {
+    generate_timed(config, &mut Timings::default())
+}
+
+/// Generate the password as a structured result, recording hashing and encoding time
+#[cfg(not(feature = "verify-only"))]
+pub fn generate_timed(config: &AppConfig, timings: &mut Timings) -> Result<GenerationResult> {
+    info_step("Generating Password Hash", 50, '-');
+    let config = &config.with_resolved_cut();
+    
+    // Generate base text
+    let base_text = build_base_text(config)?;
+    info_status(&format!("{} - Base text: {}", get_time_now(), base_text), 3);
+    
+    // The PHC string must stay verifiable, so no truncation, case or policy transforms
+    if config.emits_phc() {
+        let started = Instant::now();
+        let result = GenerationResult::new(config, argon2id_phc(&base_text, config)?);
+        timings.record("hashing", started.elapsed());
+        info_status(&format!("{} - Argon2id PHC string: {}", get_time_now(), result), 0);
+        return Ok(result);
+    }
+    
+    // Calculate hash value
+    let started = Instant::now();
+    let digest = get_config_digest(&base_text, config)?;
+    timings.record("hashing", started.elapsed());
+    let started = Instant::now();
+    
+    // Debug mode: the raw hex digest goes straight to the output
+    if config.no_transforms {
+        let result = GenerationResult::new(config, hex_encode(&digest));
+        info_status(&format!("{} - Transforms disabled, final result: {}", get_time_now(), result), 0);
+        timings.record("encoding", started.elapsed());
+        return Ok(result);
+    }
+    
+    // Retry attempts perturb the base text deterministically until the policy matches
+    let mut password = transform_digest(&digest, config)?;
+    for attempt in 1..=config.policy_retry {
+        if policy_matches(&password, config)? {
+            break;
+        }
+        let retry_text = format!("{},retry:{}", base_text, attempt);
+        password = transform_digest(&get_config_digest(&retry_text, config)?, config)?;
+        if policy_matches(&password, config)? {
+            info_status(&format!("{} - Policy satisfied on retry {}", get_time_now(), attempt), 0);
+            break;
+        }
+    }
+    check_policy(&password, config)?;
+    
+    // Generate final result
+    let result = GenerationResult::new(config, password);
+    info_status(&format!("{} - Final result: {}", get_time_now(), result), 0);
+    timings.record("encoding", started.elapsed());
+    
+    Ok(result)
+}
+
+/// Encode digest bytes and run the output transforms, prefix/suffix and policy check
+pub fn format_digest(digest: &[u8], config: &AppConfig) -> Result<String> {
+    let password = transform_digest(digest, config)?;
+    check_policy(&password, config)?;
+    Ok(password)
+}
+
+// Whether the password satisfies --policy-regex, true when no policy is set
+fn policy_matches(password: &str, config: &AppConfig) -> Result<bool> {
+    match &config.policy_regex {
+        Some(policy_regex) => Ok(Regex::new(policy_regex)?.is_match(password)),
+        None => Ok(true),
+    }
+}
+
+// Check the complete password against the policy, never echoing the password itself
+fn check_policy(password: &str, config: &AppConfig) -> Result<()> {
+    if let Some(policy_regex) = &config.policy_regex {
+        if !policy_matches(password, config)? {
+            return Err(anyhow::anyhow!("Password does not match policy regex: {}", policy_regex));
+        }
+        info_status(&format!("{} - Matches policy regex: {}", get_time_now(), policy_regex), 3);
+    }
+    Ok(())
+}
+
+// Encode digest bytes and run the output transforms and prefix/suffix
+fn transform_digest(digest: &[u8], config: &AppConfig) -> Result<String> {
+    let hash_value = encode_digest(digest, &config.output_encoding)?;
+    info_status(&format!("{} - Raw {} hash: {}", get_time_now(), config.method, hash_value), 3);
+    
+    // Run the output transforms in pipeline order
+    let mut hash_cut = hash_value;
+    for (step, transform) in config.pipeline.iter().enumerate() {
+        match transform.as_str() {
+            // Handle hash truncation (one emoji per byte, so emoji always cut by bytes)
+            "truncate" => {
+                if config.truncate_unit == "bytes" || config.output_encoding == "emoji" {
+                    if step > 0 {
+                        return Err(anyhow::anyhow!("Byte truncation must be the first transform in the pipeline"));
+                    }
+                    hash_cut = encode_digest(&digest[..digest.len().min(config.cut_length)], &config.output_encoding)?;
+                } else {
+                    hash_cut = hash_cut.chars().take(config.cut_length).collect();
+                }
+                info_status(&format!("{} - Truncated to {} {}: {}", get_time_now(), config.cut_length, config.truncate_unit, hash_cut), 3);
+            }
+            
+            // Handle end character
+            "endchar" => {
+                if let Some(end_char) = config.end_char.chars().next() {
+                    if !hash_cut.is_empty() {
+                        hash_cut.pop();
+                        hash_cut.push(end_char);
+                        info_status(&format!("{} - Added end character '{}'", get_time_now(), end_char), 3);
+                    }
+                }
+            }
+            
+            // Handle case conversion (emoji have no case)
+            "case" => {
+                // Split on a char boundary, an earlier end character may be multi-byte
+                let split = hash_cut.char_indices().map(|(index, _)| index).chain([hash_cut.len()]).nth(config.upper_start);
+                if let (true, Some(split)) = (config.output_encoding != "emoji", split) {
+                    let upper_part = hash_cut[..split].to_uppercase();
+                    let lower_part = &hash_cut[split..];
+                    hash_cut = format!("{}{}", upper_part, lower_part);
+                    info_status(&format!("{} - First {} characters uppercased", get_time_now(), config.upper_start), 3);
+                }
+            }
+            
+            other => return Err(anyhow::anyhow!("Unknown transform '{}', expected one of: {}", other, TRANSFORMS.join(", "))),
+        }
+    }
+    
+    // Place the injected symbol
+    if let Some(spec) = &config.inject_symbol {
+        hash_cut = inject_symbol(&hash_cut, spec, &config.inject_mode)?;
+        info_status(&format!("{} - Injected symbol {} ({})", get_time_now(), spec, config.inject_mode), 3);
+    }
+    
+    // Handle prefix and suffix
+    if !config.prefix.is_empty() || !config.suffix.is_empty() {
+        hash_cut = format!("{}{}{}", config.prefix, hash_cut, config.suffix);
+        info_status(&format!("{} - Added prefix '{}' and suffix '{}'", get_time_now(), config.prefix, config.suffix), 3);
+    }
+    
+    // Append the typo check character
+    if config.check_digit {
+        let check = check_digit(&hash_cut);
+        hash_cut.push(check);
+        info_status(&format!("{} - Added check character '{}'", get_time_now(), check), 3);
+    }
+    
+    Ok(hash_cut)
+}
+
+/// Check characters, 37 so the modulus is prime
+const CHECK_ALPHABET: &[u8; 37] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ*";
+
+/// Mod-37 check character over the password bytes. Each byte is weighted by a power of
+/// 31, so any single changed byte is caught unless it moved by a multiple of 37.
+pub fn check_digit(password: &str) -> char {
+    let sum = password.bytes().fold(0u32, |sum, byte| (sum * 31 + byte as u32) % 37);
+    CHECK_ALPHABET[sum as usize] as char
+}
+
+/// Whether the last character is the check character of the rest
+pub fn verify_check_digit(password: &str) -> bool {
+    let mut chars = password.chars();
+    match chars.next_back() {
+        Some(check) => check == check_digit(chars.as_str()),
+        None => false,
+    }
+}
+
+/// Ways --inject-symbol can place its symbol
+pub const INJECT_MODES: [&str; 2] = ["replace", "shift"];
+
+fn default_inject_mode() -> String {
+    "replace".to_string()
+}
+
+/// Parse an --inject-symbol spec `<char>@<pos>`, split at the last '@' so '@' itself can be injected
+pub fn parse_inject_symbol(spec: &str) -> Result<(char, usize)> {
+    let invalid = || anyhow::anyhow!("Invalid inject symbol '{}', expected <char>@<pos>", spec);
+    let (symbol, position) = spec.rsplit_once('@').ok_or_else(invalid)?;
+    let mut chars = symbol.chars();
+    let (Some(symbol), None) = (chars.next(), chars.next()) else {
+        return Err(invalid());
+    };
+    let position = position.trim().parse().map_err(|_| invalid())?;
+    Ok((symbol, position))
+}
+
+/// Put the symbol at a 0-based character position, replacing the character there or
+/// shifting the rest of the password right
+pub fn inject_symbol(password: &str, spec: &str, mode: &str) -> Result<String> {
+    let (symbol, position) = parse_inject_symbol(spec)?;
+    let mut chars: Vec<char> = password.chars().collect();
+    if position >= chars.len() {
+        return Err(anyhow::anyhow!("Inject position {} is outside the {} character output", position, chars.len()));
+    }
+    match mode {
+        "replace" => chars[position] = symbol,
+        "shift" => chars.insert(position, symbol),
+        _ => return Err(anyhow::anyhow!("Unknown inject mode '{}', expected one of: {}", mode, INJECT_MODES.join(", "))),
+    }
+    Ok(chars.into_iter().collect())
+}
+
+/// Generate the password `runs` times, erroring on the first run that differs from the first
+#[cfg(not(feature = "verify-only"))]
+pub fn verify_determinism(config: &AppConfig, runs: usize) -> Result<String> {
+    verify_determinism_with(runs, || generate_password_hash(config))
+}
+
+/// Determinism check over any generator, so the check itself can be tested
+pub fn verify_determinism_with<F>(runs: usize, mut generate: F) -> Result<String>
+where
+    F: FnMut() -> Result<String>,
+{
+    let first = generate()?;
+    for run in 2..=runs {
+        let result = generate()?;
+        if result != first {
+            return Err(anyhow::anyhow!("Run {} produced '{}' but run 1 produced '{}'", run, result, first));
+        }
+    }
+    
+    Ok(first)
+}
+
+// Get the password part of a name,password,site result
+pub fn result_get_password(result: &str, config: &AppConfig) -> String {
+    let prefix = format!("{},", config.name);
+    let suffix = format!(",{}", config.site);
+    result.strip_prefix(&prefix)
+        .and_then(|rest| rest.strip_suffix(&suffix))
+        .unwrap_or(result)
+        .to_string()
+}
+
+/// Compute a one-way verifier hash of a password, spec format: algorithm[:salt]
+pub fn compute_verifier(password: &str, spec: &str) -> Result<String> {
+    let (method, salt) = spec.split_once(':').unwrap_or((spec, ""));
+    let method = method.trim();
+    if find_algorithm(method).is_none() || method.eq_ignore_ascii_case("ARGON2ID") {
+        return Err(anyhow::anyhow!("Unsupported verifier algorithm: {}", method));
+    }
+    
+    get_string_hash(&format!("{}{}", salt, password), method)
+}
+
+/// Generate the password with every eligible algorithm, returning method and password pairs
+#[cfg(not(feature = "verify-only"))]
+pub fn compare_algorithms(config: &AppConfig, allow_weak: bool) -> Result<Vec<(String, String)>> {
+    sorted_algorithms().into_iter()
+        .filter(|algorithm| allow_weak || !algorithm.weak)
+        // Salted algorithms only run with a salt:<value>
+        .filter(|algorithm| config.salt.is_some() || !algorithm.params.contains(&"salt"))
+        .map(|algorithm| {
+            let config = AppConfig { method: algorithm.name.to_string(), ..config.clone() };
+            let result = generate_password_hash(&config)?;
+            Ok((algorithm.name.to_string(), result_get_password(&result, &config)))
+        })
+        .collect()
+}
+
+/// Configurations for `count` variants with consecutive counters starting at `count_from`.
+/// A single variant without `count_from` keeps the configured counter untouched.
+pub fn variant_configs(config: &AppConfig, count: usize, count_from: Option<usize>) -> Vec<AppConfig> {
+    if count <= 1 && count_from.is_none() {
+        return vec![config.clone()];
+    }
+    
+    let start = count_from.or(config.counter).unwrap_or(1);
+    (start..start + count)
+        .map(|counter| AppConfig { counter: Some(counter), ..config.clone() })
+        .collect()
+}
+
+/// Settings that change the hashed text or the digest itself, everything else only formats it
+#[cfg(not(feature = "verify-only"))]
+const HASH_INPUT_KEYS: [&str; 19] = [
+    "method", "argon2_memory", "argon2_iterations", "argon2_parallelism", "domain", "name", "email", "site",
+    "fields", "field_methods", "counter", "previous_password", "nonce", "salt", "dedup_fields",
+    "canonicalize_site", "unambiguous_fields", "normalize_site_case", "whole_file_sha256",
+];
+
+/// One differing setting between two configurations
+#[cfg(not(feature = "verify-only"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub a: String,
+    pub b: String,
+    pub hash_input: bool,
+}
+
+/// Diff two resolved configurations field by field, in `to_toml` order
+#[cfg(not(feature = "verify-only"))]
+pub fn explain_diff(a: &AppConfig, b: &AppConfig) -> Vec<FieldDiff> {
+    let settings = |config: &AppConfig| -> Vec<(String, String)> {
+        config.to_toml().lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    };
+    let (a, b) = (settings(a), settings(b));
+    let value = |settings: &[(String, String)], key: &str| settings.iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+        .unwrap_or_else(|| "(unset)".to_string());
+    
+    let mut keys: Vec<&String> = a.iter().map(|(key, _)| key).collect();
+    keys.extend(b.iter().map(|(key, _)| key).filter(|key| !a.iter().any(|(k, _)| k == *key)));
+    keys.into_iter()
+        .filter_map(|key| {
+            let (a, b) = (value(&a, key), value(&b, key));
+            (a != b).then(|| FieldDiff {
+                field: key.clone(),
+                a,
+                b,
+                hash_input: HASH_INPUT_KEYS.contains(&key.as_str()),
+            })
+        })
+        .collect()
+}
+
+/// Per-platform config directory: XDG on Linux, AppData on Windows, Application Support on macOS
+pub fn config_dir() -> Option<PathBuf> {
+    config_dir_with(|key| std::env::var(key).ok())
+}
+
+/// Resolve the config directory from the given environment lookup
+pub fn config_dir_with<F>(env: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let env = |key: &str| env(key).filter(|value| !value.is_empty());
+    
+    let base = if cfg!(windows) {
+        env("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    
+    base.map(|base| base.join("pass-craft"))
+}
+
+/// Answer one server connection: each `<token> <head;tail>` line gets the password, or
+/// `error: ...` when the token is wrong or the config is invalid
+#[cfg(not(feature = "verify-only"))]
+pub fn serve_connection<R: std::io::BufRead, W: std::io::Write>(reader: R, mut writer: W, token: &str) -> Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        let reply = match line.trim().split_once(' ') {
+            Some((sent, sslf)) if tokens_match(sent, token) => parse_sslf(sslf)
+                .and_then(|config| generate(&config).map(|result| result.password))
+                .unwrap_or_else(|e| format!("error: {}", e)),
+            Some(_) => "error: bad token".to_string(),
+            None => "error: expected <token> <head;tail>".to_string(),
+        };
+        writeln!(writer, "{}", reply)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+// Compare without stopping at the first differing byte
+#[cfg(not(feature = "verify-only"))]
+fn tokens_match(sent: &str, token: &str) -> bool {
+    sent.len() == token.len()
+        && sent.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Serve requests on a bound socket until the process exits, one thread per connection
+#[cfg(all(unix, not(feature = "verify-only")))]
+pub fn run_server(listener: std::os::unix::net::UnixListener, token: &str) -> Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let token = token.to_string();
+        std::thread::spawn(move || {
+            let reader = std::io::BufReader::new(&stream);
+            if let Err(e) = serve_connection(reader, &stream, &token) {
+                info_status(&format!("{} - Server connection failed: {}", get_time_now(), e), 1);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Bind the socket inside a fresh 0700 directory next to it, tighten it to 0600 and only
+/// then move it into place, so it is never reachable under the umask's permissions
+#[cfg(all(unix, not(feature = "verify-only")))]
+pub fn bind_private_socket(socket: &str) -> Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    let socket = Path::new(socket);
+    if socket.exists() {
+        return Err(anyhow::anyhow!("Socket path already exists: {}", socket.display()));
+    }
+    let name = socket.file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid socket path: {}", socket.display()))?;
+    let staging = socket.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    
+    let staged = staging.join("socket");
+    let bound = std::os::unix::net::UnixListener::bind(&staged)
+        .and_then(|listener| {
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+            fs::rename(&staged, socket)?;
+            Ok(listener)
+        });
+    fs::remove_dir_all(&staging)?;
+    Ok(bound?)
+}
+
+/// Bind the socket owner-only and serve on it
+#[cfg(all(unix, not(feature = "verify-only")))]
+pub fn start_server(socket: &str, token: &str) -> Result<()> {
+    if token.is_empty() {
+        return Err(anyhow::anyhow!("The server needs a non-empty --server-token"));
+    }
+    run_server(bind_private_socket(socket)?, token)
+}
+
+#[cfg(all(not(unix), not(feature = "verify-only")))]
+pub fn start_server(_socket: &str, _token: &str) -> Result<()> {
+    Err(anyhow::anyhow!("--server needs Unix domain sockets, not available on this platform"))
+}
+
+/// Write a starter config.toml into the config directory, never overwriting
+#[cfg(not(feature = "verify-only"))]
+pub fn init_config(dir: &Path) -> Result<PathBuf> {
+    let path = dir.join("config.toml");
+    if path.exists() {
+        return Err(anyhow::anyhow!("Config file already exists: {}", path.display()));
+    }
+    
+    fs::create_dir_all(dir)?;
+    fs::write(&path, AppConfig::default().to_toml())?;
+    Ok(path)
+}
+
+/// Convert the last valid line of a head;tail config file to a TOML file
+#[cfg(not(feature = "verify-only"))]
+pub fn migrate_to_toml(from: &str, to: &str) -> Result<AppConfig> {
+    let config = AppConfig::load_from_file(from, None, &[])?;
+    fs::write(to, config.to_toml())?;
+    Ok(config)
+}
+
+/// Read a value with `git config --get`, None if git or the key is missing
+pub fn git_config_get(key: &str) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["config", "--get", key])
+        .output()
+        .ok()?;
+    
+    if !output.status.success() {
+        debug!("git config key '{}' not found", key);
+        return None;
+    }
+    
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Look up a password in HaveIBeenPwned breach data, returning its breach count.
+/// Only the first 5 SHA1 hex characters are passed to `fetch_range` (k-anonymity).
+pub fn hibp_check<F>(password: &str, fetch_range: F) -> Result<usize>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    let digest = hex_encode(&Sha1::digest(password.as_bytes())).to_uppercase();
+    let (prefix, suffix) = digest.split_at(5);
+    let body = fetch_range(prefix)?;
+    
+    // Response lines are SUFFIX:COUNT
+    for line in body.lines() {
+        if let Some((line_suffix, count)) = line.trim().split_once(':') {
+            if line_suffix.eq_ignore_ascii_case(suffix) {
+                return Ok(count.trim().parse().unwrap_or(1));
+            }
+        }
+    }
+    
+    Ok(0)
+}
+
+/// Run a network call, retrying up to `retries` times after a failure and doubling
+/// `delay` between attempts. The final failure carries the last error.
+pub fn retry_with_backoff<T, F>(retries: usize, delay: Duration, mut call: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut delay = delay;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match call() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt > retries => return Err(anyhow::anyhow!("{} (after {} attempts)", e, attempt)),
+            Err(e) => {
+                info_status(&format!("{} - Attempt {} failed: {}, retrying in {:?}", get_time_now(), attempt, e, delay), 2);
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Fetch a HaveIBeenPwned range response via curl
+pub fn hibp_fetch_range(prefix: &str) -> Result<String> {
+    let url = format!("https://api.pwnedpasswords.com/range/{}", prefix);
+    let output = std::process::Command::new("curl")
+        .args(["-fsS", "--max-time", "10", &url])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run curl: {}", e))?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("HIBP request failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Load one configuration per valid line of a batch input file
+#[cfg(not(feature = "verify-only"))]
+pub fn batch_load_configs(file_path: &str, line_range: Option<(usize, usize)>, comment_prefixes: &[String]) -> Result<Vec<AppConfig>> {
+    let lines = sslf_load_file(file_path, "", comment_prefixes);
+    let lines = match line_range {
+        Some(line_range) => select_line_range(&lines, line_range)?,
+        None => lines,
+    };
+    
+    lines.iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let mut config = AppConfig::default();
+            AppConfig::apply_sslf_config(&mut config, line)
+                .map_err(|e| anyhow::anyhow!("Batch entry {}: {}", index + 1, e))?;
+            Ok(config)
+        })
+        .collect()
+}
+
+/// Batch input files: every regular file of a directory (sorted by name), or the path itself
+#[cfg(not(feature = "verify-only"))]
+pub fn batch_input_files(loc: &str) -> Result<Vec<String>> {
+    if !Path::new(loc).is_dir() {
+        return Ok(vec![loc.to_string()]);
+    }
+    
+    let mut files = Vec::new();
+    for entry in fs::read_dir(loc)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// File modification time in nanoseconds since the epoch
+pub fn file_mtime(loc: &str) -> Result<u128> {
+    let modified = fs::metadata(loc)?.modified()?;
+    Ok(modified.duration_since(std::time::UNIX_EPOCH)?.as_nanos())
+}
+
+/// Modification times recorded by --since-last-run, one `path;mtime:<nanos>` line per file
+#[cfg(not(feature = "verify-only"))]
+pub fn load_run_state(loc: &str) -> HashMap<String, u128> {
+    mlt_load_file(loc, "")
+        .iter()
+        .filter_map(|line| {
+            let (path, metadata) = line.rsplit_once(';')?;
+            let mtime = slkv_get("mtime", metadata, true).parse().ok()?;
+            Some((path.to_string(), mtime))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "verify-only"))]
+pub fn save_run_state(loc: &str, state: &HashMap<String, u128>) -> Result<()> {
+    let mut lines: Vec<String> = state.iter()
+        .map(|(path, mtime)| format!("{};mtime:{}", path, mtime))
+        .collect();
+    lines.sort();
+    fs::write(loc, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Files whose modification time differs from the recorded one, and the state to record
+/// once they are processed
+#[cfg(not(feature = "verify-only"))]
+pub fn modified_since_last_run(files: &[String], state: &HashMap<String, u128>) -> Result<(Vec<String>, HashMap<String, u128>)> {
+    let mut modified = Vec::new();
+    let mut next_state = state.clone();
+    for file in files {
+        let mtime = file_mtime(file)?;
+        if state.get(file) != Some(&mtime) {
+            modified.push(file.clone());
+        }
+        next_state.insert(file.clone(), mtime);
+    }
+    Ok((modified, next_state))
+}
+
+/// Options for batch processing
+#[cfg(not(feature = "verify-only"))]
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    pub save_paths: Vec<String>,
+    pub rate_limit: f64,
+    pub flush_every: usize,
+}
+
+/// Token bucket of size one: each `wait` returns no sooner than one interval after the last
+#[cfg(not(feature = "verify-only"))]
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+}
+
+#[cfg(not(feature = "verify-only"))]
+impl RateLimiter {
+    /// Limit to `per_second` items, zero or less means unlimited
+    pub fn new(per_second: f64) -> Self {
+        let interval = if per_second > 0.0 {
+            Some(Duration::from_secs_f64(1.0 / per_second))
+        } else {
+            None
+        };
+        Self { interval, next: None }
+    }
+    
+    /// Sleep until the next item may be processed
+    pub fn wait(&mut self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        
+        let now = Instant::now();
+        if let Some(next) = self.next {
+            if next > now {
+                std::thread::sleep(next - now);
+            }
+        }
+        self.next = Some(self.next.map_or(now, |next| next.max(now)) + interval);
+    }
+}
+
+/// Generate passwords for every batch configuration, appending them to the save files
+#[cfg(not(feature = "verify-only"))]
+pub fn run_batch(configs: &[AppConfig], options: &BatchOptions) -> Result<Vec<String>> {
+    let mut results = Vec::new();
+    let mut rate_limiter = RateLimiter::new(options.rate_limit);
+    let mut pending: Vec<String> = Vec::new();
+    let flush = |pending: &mut Vec<String>| -> Result<()> {
+        if !pending.is_empty() {
+            for save_path in &options.save_paths {
+                add_password_to_file(save_path, &pending.join("\n"))?;
+            }
+            pending.clear();
+        }
+        Ok(())
+    };
+    
+    let outcome = configs.iter().enumerate().try_for_each(|(index, config)| -> Result<()> {
+        rate_limiter.wait();
+        config.validate()
+            .map_err(|e| anyhow::anyhow!("Batch entry {} is invalid: {}", index + 1, e))?;
+        let result = generate_password_hash(config)?;
+        
+        pending.push(saved_entry_line(&result, None, config.counter));
+        if pending.len() >= options.flush_every.max(1) {
+            flush(&mut pending)?;
+        }
+        results.push(result);
+        Ok(())
+    });
+    
+    // Entries generated before a failure are still written out
+    flush(&mut pending)?;
+    outcome?;
+    
+    Ok(results)
+}
+
+// Display help information
+// fn print_help() {
+//     println!("Password Hash Generator v{}", env!("CARGO_PKG_VERSION"));
+//     println!();
+//     println!("A cross-platform password hash generation tool");
+//     println!();
+//     println!("USAGE:");
+//     println!("    pass-craft [COMMAND] [OPTIONS]");
+//     println!();
+//     println!("COMMANDS:");
+//     println!("    add             Add new password (default command)");
+//     println!("    generate        Generate password hash");
+//     println!();
+//     println!("OPTIONS:");
+//     println!("    --text <TEXT>           Text parameter (name:value,email:value,site:value)");
+//     println!("    --hash <HASH>           Hash parameters (method:value,cut:value,end:value,upper-start:value)");
+//     println!("    --slkv <SLKV>           String key-value configuration");
+//     println!("    --sslf <SSLF>           Secure string format configuration");
+//     println!("    --file <FILE>           Input file path");
+//     println!("    --save <FILE>           Save file path");
+//     println!("    --show-config           Show configuration information and exit");
+//     println!("    --show-platform         Show platform information and exit");
+//     println!("    --once                  Run once and exit");
+//     println!("    --mode <MODE>           Operation mode [default: interactive]");
+//     println!("    --help, -h              Show help information");
+//     println!("    --version, -v           Show version information");
+//     println!();
+//     println!("EXAMPLES:");
+//     println!("    # Basic usage");
+//     println!("    pass-craft --text \"name:john,site:example.com\" --hash \"method:sha256,cut:10\"");
+//     println!();
+//     println!("    # Read configuration from file");
+//     println!("    pass-craft --file config.txt --save passwords.txt");
+//     println!();
+//     println!("    # Show platform information");
+//     println!("    pass-craft --show-platform");
+//     println!();
+//     println!("    # Show configuration");
+//     println!("    pass-craft --show-config --text \"name:test,site:example.com\"");
+// }
+
+#[cfg(all(test, not(feature = "verify-only")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_info() {
+        let platform = PlatformInfo::new();
+        
+        // Verify platform information is not empty
+        assert!(!platform.os.is_empty());
+        assert!(!platform.arch.is_empty());
+        assert!(!platform.family.is_empty());
+        
+        // Verify display format
+        let display = platform.display();
+        assert!(display.contains(&platform.os));
+        assert!(display.contains(&platform.arch));
+    }
+
+    #[test]
+    fn test_info_step_alignment() {
+        // Test various message lengths
+        info_step("Configuration", 50, '=');
+        info_step("Generating Password Hash", 50, '-');
+        info_step("Test", 20, '*');
+        info_step("A", 10, '-');
+        
+        // Test long messages (should display directly)
+        info_step("This is a very long message that exceeds the specified length", 30, '+');
+    }
+
+    #[test]
+    fn test_info_status() {
+        // Test all status types
+        info_status("Success message", 0);
+        info_status("Error message", 1);
+        info_status("Warning message", 2);
+        info_status("Info message", 3);
+        info_status("Default info message", 99);
+    }
+
+    #[test]
+    fn test_get_time_now() {
+        let time1 = get_time_now();
+        let time2 = get_time_now();
+        
+        // Verify time format
+        assert_eq!(time1.len(), 19); // "YYYY-MM-DD HH:MM:SS"
+        assert!(time1.contains('-')); // Contains date separator
+        assert!(time1.contains(':')); // Contains time separator
+        
+        // Two calls should get different times (or at least same format)
+        assert_eq!(time1.len(), time2.len());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let valid_config = AppConfig {
+            method: "SHA256".to_string(),
+            cut_length: 8,
+            end_char: "!".to_string(),
+            upper_start: 3,
+            name: "test".to_string(),
+            email: "test@example.com".to_string(),
+            site: "example.com".to_string(),
+            input_file: None,
+            platform_identifier: "test".to_string(),
+            ..Default::default()
+        };
+        
+        assert!(valid_config.validate().is_ok());
+        
+        // Test invalid configurations
+        let invalid_configs = [
+            AppConfig { method: "INVALID".to_string(), ..valid_config.clone() }, // Invalid algorithm
+            AppConfig { cut_length: 0, ..valid_config.clone() }, // Cut length too small
+            AppConfig { cut_length: 65, ..valid_config.clone() }, // Cut length too large
+            AppConfig { upper_start: 10, ..valid_config.clone() }, // Upper start exceeds cut length
+        ];
+        
+        for (i, config) in invalid_configs.iter().enumerate() {
+            assert!(config.validate().is_err(), "Test case {} should fail", i);
+        }
+    }
+
+    #[test]
+    fn test_generate_password_hash() {
+        let config = AppConfig {
+            method: "MD5".to_string(), // Use MD5 for easier testing
+            cut_length: 6,
+            end_char: "!".to_string(),
+            upper_start: 2,
+            name: "test".to_string(),
+            email: "test@example.com".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        let result = generate_password_hash(&config).unwrap();
+        assert!(result.starts_with("test,"));
+        assert!(result.ends_with(",example.com"));
+        assert!(result.contains("!"));
+    }
+
+    #[test]
+    fn test_hash_functions() {
+        // Test various hash algorithms
+        let test_string = "hello world";
+        
+        let md5_hash = get_string_hash(test_string, "MD5").unwrap();
+        let sha1_hash = get_string_hash(test_string, "SHA1").unwrap();
+        let sha256_hash = get_string_hash(test_string, "SHA256").unwrap();
+        let sha512_hash = get_string_hash(test_string, "SHA512").unwrap();
+        let sha3_256_hash = get_string_hash(test_string, "sha3-256").unwrap();
+        let sha3_512_hash = get_string_hash(test_string, "SHA3-512").unwrap();
+        let blake3_hash = get_string_hash(test_string, "blake3").unwrap();
+        
+        // Verify hash lengths
+        assert_eq!(md5_hash.len(), 32);
+        assert_eq!(sha1_hash.len(), 40);
+        assert_eq!(sha256_hash.len(), 64);
+        assert_eq!(sha512_hash.len(), 128);
+        assert_eq!(sha3_256_hash.len(), 64);
+        assert_eq!(sha3_512_hash.len(), 128);
+        assert_eq!(blake3_hash.len(), 64);
+        
+        // Verify known hash values
+        assert_eq!(md5_hash, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+        assert_eq!(get_string_hash("", "SHA3-256").unwrap(), "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a");
+        assert_eq!(get_string_hash("", "SHA3-512").unwrap(), "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26");
+        assert_eq!(get_string_hash("", "BLAKE3").unwrap(), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        
+        // Argon2id needs a config, unknown names are errors rather than panics
+        assert!(get_string_digest(test_string, "argon2id").is_err());
+        assert!(get_string_hash(test_string, "SHA0").is_err());
+        
+        // Official BLAKE3 vectors (bytes i % 251) across the 1 KiB chunk boundary and an odd
+        // chunk count, fed through HashBuilder in uneven pieces
+        let pattern = |len: usize| (0..len).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+        for (len, expected) in [
+            (1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"),
+            (1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"),
+            (2048, "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a"),
+            (3073, "7124b49501012f81cc7f11ca069ec9226cecb8a2c850cfe644e327d22d3e1cd3"),
+        ] {
+            let mut builder = HashBuilder::new("BLAKE3").unwrap();
+            for piece in pattern(len).chunks(100) {
+                builder.update(piece);
+            }
+            assert_eq!(hex_encode(&builder.digest().unwrap()), expected, "{} bytes", len);
+        }
+        
+        // SHA3 across the sponge rate (136 bytes for SHA3-256, 72 for SHA3-512)
+        let sha3 = |method: &str, len: usize| {
+            let mut builder = HashBuilder::new(method).unwrap();
+            for piece in pattern(len).chunks(50) {
+                builder.update(piece);
+            }
+            hex_encode(&builder.digest().unwrap())
+        };
+        assert_eq!(sha3("SHA3-256", 136), "cf3ccff92480a29160c2d38317c430e14749bfee1788106957dfe73f8c4930e5");
+        assert_eq!(sha3("SHA3-256", 137), "ce9d7dc90913ee5d92745019479a5352c6d6279bef18ed07dc0a83ee8084daca");
+        assert_eq!(sha3("SHA3-512", 72), "5d63f2bbe971a983ac6847480106e4e1264ee3a0befd79954914e1d86e795b2e18238f12fc5e46cb9cc78efdec610a93647cc04e1c23d8caaa6a58c21dd26c07");
+        
+        // The hyphenated name survives hash config parsing
+        let mut config = AppConfig::default();
+        AppConfig::apply_hash_config(&mut config, "method:sha3-256,cut:12").unwrap();
+        assert_eq!((config.method.as_str(), config.cut_length), ("sha3-256", 12));
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_auto_counter_rotation() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-counter-{}.md", std::process::id()));
+        let save_path = save_path.to_str().unwrap();
+        let _ = fs::remove_file(save_path);
+        
+        let mut config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        // Rotate three times, each run counting the entries saved so far
+        let mut results = Vec::new();
+        for expected in 0..3 {
+            let count = count_saved_entries(save_path, &config.name, &config.site);
+            assert_eq!(count, expected);
+            
+            config.counter = Some(count);
+            let result = generate_password_hash(&config).unwrap();
+            add_password_to_file(save_path, &html_comment_wrap(&result)).unwrap();
+            results.push(result);
+        }
+        
+        // Entries for other sites and config comments are not counted
+        add_password_to_file(save_path, "<!-- john,AbC123!,other.com -->").unwrap();
+        add_password_to_file(save_path, "<!-- name:john,site:example.com -->").unwrap();
+        assert_eq!(count_saved_entries(save_path, "john", "example.com"), 3);
+        
+        // Every rotation yields a new password
+        assert_ne!(results[0], results[1]);
+        assert_ne!(results[1], results[2]);
+        
+        // URL sites keep their ':' and are still counted, so the counter advances
+        config.site = "https://a.com".to_string();
+        for expected in 0..2 {
+            assert_eq!(count_saved_entries(save_path, &config.name, &config.site), expected);
+            let result = generate_password_hash(&config).unwrap();
+            add_password_to_file(save_path, &saved_entry_line(&result, None, None)).unwrap();
+        }
+        
+        fs::remove_file(save_path).unwrap();
+    }
+
+    #[test]
+    fn test_site_rules() {
+        let rules_path = std::env::temp_dir().join(format!("pass-craft-rules-{}.txt", std::process::id()));
+        let rules_path = rules_path.to_str().unwrap();
+        fs::write(rules_path, "# site rules\nexample.com;method:sha256,cut:12\nlegacy.org;method:md5\n").unwrap();
+        
+        let rules = load_site_rules(rules_path, &[]);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules["example.com"].method.as_deref(), Some("sha256"));
+        assert_eq!(rules["example.com"].cut_length, Some(12));
+        
+        // A listed site gets its rule
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,site:example.com", "--site-rules", rules_path]);
+        let config = AppConfig::from_args(&args).unwrap();
+        assert_eq!(config.method, "sha256");
+        assert_eq!(config.cut_length, 12);
+        
+        // An unlisted site keeps the defaults
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,site:unknown.net", "--site-rules", rules_path]);
+        let config = AppConfig::from_args(&args).unwrap();
+        assert_eq!(config.method, AppConfig::default().method);
+        assert_eq!(config.cut_length, AppConfig::default().cut_length);
+        
+        // Command line hash parameters override the rule
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,site:example.com", "--hash", "cut:10", "--site-rules", rules_path]);
+        let config = AppConfig::from_args(&args).unwrap();
+        assert_eq!(config.method, "sha256");
+        assert_eq!(config.cut_length, 10);
+        
+        fs::remove_file(rules_path).unwrap();
+    }
+
+    #[test]
+    fn test_emit_verifier() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        let result = generate_password_hash(&config).unwrap();
+        let password = result_get_password(&result, &config);
+        assert_eq!(result, format!("john,{},example.com", password));
+        
+        // Verifier matches an independent recomputation from the printed password
+        let verifier = compute_verifier(&password, "sha256:pepper").unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(format!("pepper{}", password).as_bytes());
+        assert_eq!(verifier, format!("{:x}", hasher.finalize()));
+        
+        // Without salt the verifier is a plain digest of the password
+        assert_eq!(compute_verifier(&password, "MD5").unwrap(), format!("{:x}", md5::compute(password.as_bytes())));
+        
+        assert!(compute_verifier(&password, "crc32").is_err());
+    }
+
+    #[test]
+    fn test_line_range() {
+        assert_eq!(parse_line_range("100:200").unwrap(), (100, 200));
+        assert!(parse_line_range("0:2").is_err());
+        assert!(parse_line_range("5:2").is_err());
+        assert!(parse_line_range("5").is_err());
+        
+        let input_path = std::env::temp_dir().join(format!("pass-craft-batch-{}.txt", std::process::id()));
+        let input_path = input_path.to_str().unwrap();
+        fs::write(input_path, "# batch input\nname:a,site:a.com\n\nname:b,site:b.com\nname:c,site:c.com;method:md5\nname:d,site:d.com\n").unwrap();
+        
+        // Range counts lines after comments and blank lines are removed
+        let configs = batch_load_configs(input_path, Some((2, 3)), &[]).unwrap();
+        let results = run_batch(&configs, &BatchOptions::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].starts_with("b,") && results[0].ends_with(",b.com"));
+        assert!(results[1].starts_with("c,") && results[1].ends_with(",c.com"));
+        assert_eq!(configs[1].method, "md5");
+        
+        assert_eq!(batch_load_configs(input_path, None, &[]).unwrap().len(), 4);
+        assert!(batch_load_configs(input_path, Some((3, 5)), &[]).is_err());
+        
+        // Single config loading takes the last line of the range
+        let config = AppConfig::load_from_file(input_path, Some((1, 2)), &[]).unwrap();
+        assert_eq!(config.name, "b");
+        
+        fs::remove_file(input_path).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_to_toml() {
+        let from_path = std::env::temp_dir().join(format!("pass-craft-migrate-{}.txt", std::process::id()));
+        let to_path = std::env::temp_dir().join(format!("pass-craft-migrate-{}.toml", std::process::id()));
+        let (from_path, to_path) = (from_path.to_str().unwrap(), to_path.to_str().unwrap());
+        fs::write(from_path, "<!-- john,B5CB304+,john.com -->\nname:john,email:john@gmail.com,site:john.com;method:sha512,cut:8,end:\",upper-start:5\n").unwrap();
+        
+        let migrated = migrate_to_toml(from_path, to_path).unwrap();
+        assert_eq!(migrated.name, "john");
+        assert_eq!(migrated.end_char, "\"");
+        
+        // The TOML file reloads to an equivalent configuration
+        let reloaded = AppConfig::load_from_file(to_path, None, &[]).unwrap();
+        assert_eq!(reloaded, migrated);
+        assert_eq!(generate_password_hash(&reloaded).unwrap(), generate_password_hash(&migrated).unwrap());
+        
+        assert!(AppConfig::from_toml("cut_length = \"8\"").is_err());
+        assert!(AppConfig::from_toml("unknown = 1").is_err());
+        
+        fs::remove_file(from_path).unwrap();
+        fs::remove_file(to_path).unwrap();
+    }
+
+    #[test]
+    fn test_hibp_check() {
+        let password = "B5CB304+";
+        let digest = get_string_hash(password, "SHA1").unwrap().to_uppercase();
+        let suffix = digest[5..].to_string();
+        
+        // Mocked range endpoint only ever receives the 5 character prefix
+        let found = |prefix: &str| -> Result<String> {
+            assert_eq!(prefix, &digest[..5]);
+            Ok(format!("0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n{}:42\r\n", suffix))
+        };
+        assert_eq!(hibp_check(password, found).unwrap(), 42);
+        
+        let not_found = |prefix: &str| -> Result<String> {
+            assert_eq!(prefix.len(), 5);
+            Ok("0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n".to_string())
+        };
+        assert_eq!(hibp_check(password, not_found).unwrap(), 0);
+        
+        let unavailable = |_: &str| -> Result<String> { Err(anyhow::anyhow!("offline")) };
+        assert!(hibp_check(password, unavailable).is_err());
+    }
+
+    #[test]
+    fn test_base_text_deterministic() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            counter: Some(2),
+            ..Default::default()
+        };
+        
+        let first = build_base_text(&config).unwrap();
+        let second = build_base_text(&config.clone()).unwrap();
+        assert_eq!(first.as_bytes(), second.as_bytes());
+        assert_eq!(first, "john,john@gmail.com,john.com,2");
+        
+        let config = AppConfig { counter: None, ..config };
+        assert_eq!(build_base_text(&config).unwrap(), "john,john@gmail.com,john.com");
+    }
+
+    #[test]
+    fn test_truncate_unit() {
+        assert_eq!(base64_encode(b"hello world"), "aGVsbG8gd29ybGQ=");
+        assert_eq!(base64_encode(b"hi"), "aGk=");
+        
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            output_encoding: "base64".to_string(),
+            cut_length: 6,
+            end_char: "".to_string(),
+            upper_start: 0,
+            ..Default::default()
+        };
+        let digest = get_string_digest(&build_base_text(&config).unwrap(), &config.method).unwrap();
+        
+        // Character truncation keeps the first 6 base64 characters
+        let chars = generate_password_hash(&config).unwrap();
+        let chars_password = result_get_password(&chars, &config);
+        assert_eq!(chars_password, base64_encode(&digest)[..6]);
+        
+        // Byte truncation encodes the first 6 digest bytes, giving 8 base64 characters
+        let config = AppConfig { truncate_unit: "bytes".to_string(), ..config };
+        let bytes = generate_password_hash(&config).unwrap();
+        let bytes_password = result_get_password(&bytes, &config);
+        assert_eq!(bytes_password, base64_encode(&digest[..6]));
+        assert_eq!(bytes_password.len(), 8);
+        assert!(bytes_password.starts_with(&chars_password));
+        
+        // Hex with the default char unit is unchanged
+        assert_eq!(AppConfig::default().truncate_unit, "chars");
+        assert!(AppConfig { truncate_unit: "words".to_string(), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_from_git() {
+        let git_config: HashMap<&str, &str> = [("user.name", "john"), ("user.email", "john@gmail.com")].into();
+        let lookup = |key: &str| git_config.get(key).map(|value| value.to_string());
+        
+        let mut config = AppConfig::default();
+        AppConfig::apply_git_config(&mut config, lookup);
+        assert_eq!(config.name, "john");
+        assert_eq!(config.email, "john@gmail.com");
+        
+        // --text is applied afterwards and overrides git values
+        AppConfig::apply_text_config(&mut config, "name:jane,site:example.com").unwrap();
+        assert_eq!(config.name, "jane");
+        assert_eq!(config.email, "john@gmail.com");
+        
+        // Missing git or keys leave the fields untouched
+        let mut config = AppConfig::default();
+        AppConfig::apply_git_config(&mut config, |_| None);
+        assert_eq!(config, AppConfig::default());
+    }
+
+    #[test]
+    fn test_print_digest() {
+        let config = AppConfig {
+            method: "SHA256".to_string(),
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        
+        // Same as: printf 'john,john@gmail.com,john.com' | sha256sum
+        assert_eq!(raw_digest(&config).unwrap(), "6d39a810723c5665995c2243ba9fa010d8a2b4f39d997430787c6bb00bc5a3f0");
+    }
+
+    #[test]
+    fn test_save_fan_out() {
+        let dir = std::env::temp_dir();
+        let personal = dir.join(format!("pass-craft-fanout-a-{}.md", std::process::id()));
+        let shared = dir.join(format!("pass-craft-fanout-b-{}.md", std::process::id()));
+        let broken = dir.join(format!("pass-craft-missing-{}", std::process::id())).join("c.md");
+        let (personal, shared, broken) = (personal.to_str().unwrap(), shared.to_str().unwrap(), broken.to_str().unwrap());
+        
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,site:example.com", "--save", personal, "--save", broken, "--save", shared]);
+        let config = AppConfig::from_args(&args).unwrap();
+        assert_eq!(config.output_files.len(), 3);
+        
+        let result = generate_password_hash(&config).unwrap();
+        let outcomes = save_result(&config, &result);
+        
+        // The unwritable path fails on its own, the others still get the entry
+        assert!(outcomes[0].1.is_ok());
+        assert!(outcomes[1].1.is_err());
+        assert!(outcomes[2].1.is_ok());
+        for path in [personal, shared] {
+            assert!(fs::read_to_string(path).unwrap().contains(&saved_entry_line(&result, None, None)));
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_config_fingerprint() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        // Equal configs share a fingerprint, file paths are not part of it
+        let same = AppConfig { output_files: vec!["passwords.md".to_string()], ..config.clone() };
+        assert_eq!(config_fingerprint(&config), config_fingerprint(&same));
+        assert_eq!(config_fingerprint(&config).len(), 64);
+        
+        // Any setting that affects the password changes it
+        let changed = [
+            AppConfig { cut_length: 9, ..config.clone() },
+            AppConfig { site: "example.org".to_string(), ..config.clone() },
+            AppConfig { counter: Some(1), ..config.clone() },
+        ];
+        for other in &changed {
+            assert_ne!(config_fingerprint(&config), config_fingerprint(other));
+        }
+        
+        // --hash-whole-file content is covered too, though only its digest is written out
+        let whole_a = AppConfig { whole_file: Some("first content\n".to_string()), ..config.clone() };
+        let whole_b = AppConfig { whole_file: Some("other content\n".to_string()), ..config.clone() };
+        assert_ne!(generate_password_hash(&whole_a).unwrap(), generate_password_hash(&whole_b).unwrap());
+        assert_ne!(config_fingerprint(&whole_a), config_fingerprint(&whole_b));
+        let diff = explain_diff(&whole_a, &whole_b);
+        assert!(diff.len() == 1 && diff[0].field == "whole_file_sha256" && diff[0].hash_input);
+        assert!(!whole_a.to_toml().contains("first content"));
+        assert_eq!(AppConfig::from_toml(&whole_a.to_toml()).unwrap().whole_file, None);
+    }
+
+    #[test]
+    fn test_prefix_suffix() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        let plain = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        
+        // Applied after all transforms, independent of end_char
+        let decorated = AppConfig { prefix: "P-".to_string(), suffix: "#9".to_string(), ..config.clone() };
+        let password = result_get_password(&generate_password_hash(&decorated).unwrap(), &decorated);
+        assert_eq!(password, format!("P-{}#9", plain));
+        
+        // Prefix and suffix count towards the policy length
+        let capped = AppConfig { policy_regex: Some("^.{8,10}$".to_string()), ..decorated.clone() };
+        assert!(generate_password_hash(&capped).is_err());
+        let capped = AppConfig { policy_regex: Some("^P-.{8}#9$".to_string()), ..decorated };
+        assert!(generate_password_hash(&capped).is_ok());
+        
+        assert!(AppConfig { policy_regex: Some("(".to_string()), ..config }.validate().is_err());
+    }
+
+    #[test]
+    fn test_emoji_encoding() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            output_encoding: "emoji".to_string(),
+            cut_length: 6,
+            end_char: "".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        
+        let config = AppConfig { allow_emoji: true, ..config };
+        assert!(config.validate().is_ok());
+        
+        let first = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        let second = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(first, second);
+        assert_eq!(first.chars().count(), 6);
+        assert!(first.chars().all(|c| EMOJI_SET.contains(&c)));
+        
+        // The end character replaces the last emoji
+        let config = AppConfig { end_char: "!".to_string(), ..config };
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.chars().count(), 6);
+        assert!(password.ends_with('!'));
+    }
+
+    #[test]
+    fn test_hash_flags() {
+        let base = ["pass-craft", "--text", "name:john,site:example.com", "--hash", "method:md5,cut:12,end:#,upper-start:4"];
+        let config = AppConfig::from_args(&CliArgs::parse_from(base)).unwrap();
+        assert_eq!((config.method.as_str(), config.cut_length, config.end_char.as_str(), config.upper_start), ("md5", 12, "#", 4));
+        
+        // Each flag sets its field and wins over the packed form
+        let with_flags = |flags: &[&str]| {
+            let args: Vec<&str> = base.iter().chain(flags.iter()).copied().collect();
+            AppConfig::from_args(&CliArgs::parse_from(args)).unwrap()
+        };
+        assert_eq!(with_flags(&["--method", "sha256"]).method, "sha256");
+        assert_eq!(with_flags(&["--cut", "10"]).cut_length, 10);
+        assert_eq!(with_flags(&["--end", "+"]).end_char, "+");
+        assert_eq!(with_flags(&["--upper-start", "2"]).upper_start, 2);
+        let salted = ["pass-craft", "--text", "name:john,site:example.com", "--hash", "method:md5,salt:packed"];
+        assert_eq!(AppConfig::from_args(&CliArgs::parse_from(salted)).unwrap().salt.as_deref(), Some("packed"));
+        let args: Vec<&str> = salted.iter().chain(["--salt", "flag"].iter()).copied().collect();
+        assert_eq!(AppConfig::from_args(&CliArgs::parse_from(args)).unwrap().salt.as_deref(), Some("flag"));
+        
+        // Flags alone work without --hash
+        let config = AppConfig::from_args(&CliArgs::parse_from(["pass-craft", "--method", "sha1"])).unwrap();
+        assert_eq!(config.method, "sha1");
+        assert_eq!(config.cut_length, AppConfig::default().cut_length);
+        
+        // Flags also win over a --file config
+        let file = std::env::temp_dir().join(format!("pass-craft-flags-{}.txt", std::process::id()));
+        fs::write(&file, "name:john,email:john@gmail.com,site:example.com;method:sha256,cut:12,end:!,upper-start:4\n").unwrap();
+        let from_file = |flags: &[&str]| {
+            let args: Vec<&str> = ["pass-craft", "--file", file.to_str().unwrap()].iter().chain(flags.iter()).copied().collect();
+            let config = AppConfig::from_args(&CliArgs::parse_from(args)).unwrap();
+            generate_password_hash(&config).unwrap()
+        };
+        let file_only = from_file(&[]);
+        for flags in [["--method", "md5"], ["--cut", "6"], ["--end", "+"], ["--upper-start", "2"], ["--salt", "abc"]] {
+            assert_ne!(from_file(&flags), file_only, "{:?}", flags);
+        }
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_compare_algorithms() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        let rows = compare_algorithms(&config, false).unwrap();
+        let methods: Vec<&str> = rows.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(methods, ["BLAKE3", "SHA256", "SHA3-256", "SHA3-512", "SHA512", "SHAKE128", "SHAKE256"]);
+        
+        // Weak algorithms only with --allow-weak, one row each
+        let rows = compare_algorithms(&config, true).unwrap();
+        assert_eq!(rows.len(), ALGORITHMS.len() - 1);
+        let sha512 = AppConfig { method: "SHA512".to_string(), ..config.clone() };
+        assert_eq!(rows[6], ("SHA512".to_string(), result_get_password(&generate_password_hash(&sha512).unwrap(), &config)));
+        
+        assert_eq!(find_algorithm(" sha256 ").map(|algorithm| algorithm.output_bytes), Some(32));
+        assert!(find_algorithm("crc32").is_none());
+    }
+
+    #[test]
+    fn test_strict_utf8() {
+        let input_path = std::env::temp_dir().join(format!("pass-craft-utf8-{}.txt", std::process::id()));
+        let input_path = input_path.to_str().unwrap();
+        fs::write(input_path, b"name:j\xffohn,site:example.com\nname:jane,site:example.com\n").unwrap();
+        
+        // Strict mode reports the offset of the first invalid byte
+        let err = read_text_file(input_path, true).unwrap_err().to_string();
+        assert!(err.contains("byte offset 6"), "{}", err);
+        let args = CliArgs::parse_from(["pass-craft", "--file", input_path, "--strict-utf8"]);
+        assert!(AppConfig::from_args(&args).is_err());
+        
+        // Default mode replaces the byte and keeps every line
+        let lines = mlt_load_file(input_path, "");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "name:j\u{fffd}ohn,site:example.com");
+        let args = CliArgs::parse_from(["pass-craft", "--file", input_path]);
+        assert_eq!(AppConfig::from_args(&args).unwrap().name, "jane");
+        
+        fs::remove_file(input_path).unwrap();
+    }
+
+    #[test]
+    fn test_field_order_independence() {
+        let generate = |args: &[&str]| {
+            let config = AppConfig::from_args(&CliArgs::parse_from(args)).unwrap();
+            generate_password_hash(&config).unwrap()
+        };
+        
+        // Key order in the input never changes the output
+        let first = generate(&["pass-craft", "--text", "name:a,email:a@b.c,site:b"]);
+        let second = generate(&["pass-craft", "--text", "site:b,email:a@b.c,name:a"]);
+        assert_eq!(first, second);
+        assert_eq!(first, generate(&["pass-craft", "--text", "name:a,email:a@b.c,site:b", "--fields", "name,email,site"]));
+        
+        // Selecting fields defines the canonical order
+        let name_site = generate(&["pass-craft", "--text", "site:b,name:a", "--fields", "name,site"]);
+        assert_ne!(name_site, first);
+        let config = AppConfig { name: "a".to_string(), site: "b".to_string(), fields: vec!["name".to_string(), "site".to_string()], ..Default::default() };
+        assert_eq!(build_base_text(&config).unwrap(), "a,b");
+        
+        assert!(AppConfig { fields: vec!["phone".to_string()], ..Default::default() }.validate().is_err());
+        assert!(AppConfig { fields: vec!["name".to_string(), "name".to_string()], ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_timings() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            ..Default::default()
+        };
+        
+        let mut timings = Timings::default();
+        timings.record("reading", Duration::from_millis(2));
+        let result = generate_password_hash_timed(&config, &mut timings).unwrap();
+        assert_eq!(result, generate_password_hash(&config).unwrap());
+        timings.record("saving", Duration::from_millis(1));
+        timings.record("saving", Duration::from_millis(1));
+        
+        let phases: Vec<&str> = timings.phases.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(phases, ["reading", "hashing", "encoding", "saving"]);
+        assert_eq!(timings.phases[3].1, Duration::from_millis(2));
+        
+        let report = timings.report();
+        assert_eq!(report.len(), 4);
+        assert!(report[0].starts_with("reading") && report[0].ends_with("2.000 ms"));
+        
+        let json = timings.to_json();
+        assert!(json.starts_with("{\"reading\": 2.000, \"hashing\": "));
+        assert!(json.ends_with("\"saving\": 2.000}"));
+    }
+
+    #[test]
+    fn test_chain_previous() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-chain-{}.md", std::process::id()));
+        let save_path = save_path.to_str().unwrap();
+        let _ = fs::remove_file(save_path);
+        let args = ["pass-craft", "--text", "name:john,site:example.com", "--save", save_path, "--chain-previous"];
+        
+        // First rotation has no prior and falls back to the normal derivation
+        let config = AppConfig::from_args(&CliArgs::parse_from(args)).unwrap();
+        assert_eq!(config.previous_password, None);
+        let first = generate_password_hash(&config).unwrap();
+        assert_eq!(first, generate_password_hash(&AppConfig { previous_password: None, ..config.clone() }).unwrap());
+        add_password_to_file(save_path, &html_comment_wrap(&first)).unwrap();
+        add_password_to_file(save_path, "<!-- john,Other1!,other.com -->").unwrap();
+        
+        // Later rotations chain from the stored password, stable for the same prior
+        let config = AppConfig::from_args(&CliArgs::parse_from(args)).unwrap();
+        assert_eq!(config.previous_password.as_deref(), Some(result_get_password(&first, &config).as_str()));
+        let chained = generate_password_hash(&config).unwrap();
+        assert_ne!(chained, first);
+        assert_eq!(chained, generate_password_hash(&config.clone()).unwrap());
+        
+        fs::remove_file(save_path).unwrap();
+    }
+
+    #[test]
+    fn test_save_file_detection() {
+        let input_path = std::env::temp_dir().join(format!("pass-craft-savefile-{}.md", std::process::id()));
+        let input_path = input_path.to_str().unwrap();
+        fs::write(input_path, "<!-- john,8C636ff!,example.com -->\n\n<!-- test,560a430!,example.com -->\n<!-- john,B5CB304+,john.com -->\n").unwrap();
+        
+        let warning = save_file_warning(input_path).unwrap();
+        assert!(warning.contains("looks like a password save file"));
+        
+        // A config file that also collects results (README workflow) is fine
+        let lines: Vec<String> = fs::read_to_string("passwords.example.md").unwrap().lines().map(String::from).collect();
+        assert!(!looks_like_save_file(&lines));
+        assert!(!looks_like_save_file(&["name:john,site:example.com".to_string()]));
+        assert!(!looks_like_save_file(&[]));
+        
+        fs::remove_file(input_path).unwrap();
+    }
+
+    #[test]
+    fn test_end_char_short_output() {
+        let config = AppConfig { cut_length: 1, upper_start: 0, ..Default::default() };
+        assert_eq!(config.warnings().len(), 1);
+        assert!(config.warnings()[0].contains("1 of 1 characters"));
+        assert!(config.validate().is_ok());
+        
+        // Strict mode turns the warning into an error
+        let strict = AppConfig { strict: true, ..config.clone() };
+        assert!(strict.validate().is_err());
+        
+        // No end character or a long enough output is fine
+        assert!(AppConfig { end_char: "".to_string(), ..config }.warnings().is_empty());
+        assert!(AppConfig { cut_length: 4, strict: true, ..Default::default() }.validate().is_ok());
+        assert!(!AppConfig { cut_length: 3, ..Default::default() }.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_init_config() {
+        let base = std::env::temp_dir().join(format!("pass-craft-config-{}", std::process::id()));
+        let base_str = base.to_str().unwrap().to_string();
+        let env = |key: &str| match key {
+            "XDG_CONFIG_HOME" | "APPDATA" => Some(base_str.clone()),
+            "HOME" => Some(base_str.clone()),
+            _ => None,
+        };
+        
+        let dir = config_dir_with(env).unwrap();
+        assert!(dir.starts_with(&base));
+        assert!(dir.ends_with("pass-craft"));
+        
+        // Starter config lands in the resolved directory and loads back
+        let path = init_config(&dir).unwrap();
+        assert_eq!(path, dir.join("config.toml"));
+        let config = AppConfig::load_from_file(path.to_str().unwrap(), None, &[]).unwrap();
+        assert_eq!(config, AppConfig::default());
+        assert!(init_config(&dir).is_err());
+        
+        assert_eq!(config_dir_with(|_| None), None);
+        
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let configs = vec![AppConfig { name: "john".to_string(), ..Default::default() }; 5];
+        
+        // 50 per second: 5 items need at least 4 intervals of 20ms
+        let started = Instant::now();
+        let options = BatchOptions { rate_limit: 50.0, ..Default::default() };
+        assert_eq!(run_batch(&configs, &options).unwrap().len(), 5);
+        assert!(started.elapsed() >= Duration::from_millis(75), "{:?}", started.elapsed());
+        
+        // Zero means unlimited, the limiter never sleeps
+        let mut rate_limiter = RateLimiter::new(0.0);
+        let started = Instant::now();
+        for _ in 0..100 {
+            rate_limiter.wait();
+        }
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_token_errors() {
+        let mut config = AppConfig::default();
+        
+        // Offending token index (1-based) and content are reported
+        let err = AppConfig::apply_hash_config(&mut config, "method:sha256,cutx,end:!").unwrap_err();
+        assert_eq!(err, TokenError::new(2, "cutx", "expected key:value"));
+        let err = AppConfig::apply_hash_config(&mut config, "method:sha256,end:!,cut:ten").unwrap_err();
+        assert_eq!((err.index, err.token.as_str()), (3, "cut:ten"));
+        let err = AppConfig::apply_text_config(&mut config, "name:john,:x").unwrap_err();
+        assert_eq!((err.index, err.token.as_str()), (2, ":x"));
+        
+        // Empty tokens (trailing commas) and unknown keys are still accepted
+        assert!(AppConfig::apply_sslf_config(&mut config, "name:john,site:john.com,;method:sha512,note:x").is_ok());
+        
+        // AppConfig::from_args reports every malformed source
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,sitex", "--hash", "cut:8,upper-start:three"]);
+        let err = AppConfig::from_args(&args).unwrap_err().to_string();
+        assert!(err.contains("--text token 2 'sitex'"), "{}", err);
+        assert!(err.contains("--hash token 2 'upper-start:three'"), "{}", err);
+    }
+
+    #[test]
+    fn test_variant_configs() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        let passwords = |count, count_from| -> Vec<String> {
+            variant_configs(&config, count, count_from).iter()
+                .map(|config| generate_password_hash(config).unwrap())
+                .collect()
+        };
+        
+        // A single default variant leaves the base text alone
+        assert_eq!(passwords(1, None), vec![generate_password_hash(&config).unwrap()]);
+        
+        // --count-from 1 matches the default start
+        assert_eq!(passwords(2, Some(1)), passwords(2, None));
+        
+        // --count 2 --count-from 3 yields variants 3 and 4
+        let shifted = passwords(2, Some(3));
+        assert_eq!(shifted.len(), 2);
+        assert_eq!(shifted[0], passwords(4, None)[2]);
+        assert_eq!(shifted[1], passwords(4, None)[3]);
+        assert_ne!(shifted[0], shifted[1]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_save_file() {
+        let base = std::env::temp_dir().join(format!("pass-craft-symlink-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        let real = base.join("real.md");
+        let link = base.join("link.md");
+        fs::write(&real, "<!-- john,old,john.com -->").unwrap();
+        std::os::unix::fs::symlink("real.md", &link).unwrap();
+        let mut config = AppConfig { output_files: vec![link.to_string_lossy().to_string()], ..Default::default() };
+        
+        // Default: write through the link, the link stays a link
+        let outcomes = save_result(&config, "john,new,john.com");
+        assert!(outcomes[0].1.is_ok());
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert!(fs::read_to_string(&real).unwrap().contains("<!-- john,new,john.com;version:"));
+        
+        // --no-follow-symlinks: refuse and leave the real file alone
+        config.follow_symlinks = false;
+        let outcomes = save_result(&config, "john,newer,john.com");
+        assert!(outcomes[0].1.is_err());
+        assert!(!fs::read_to_string(&real).unwrap().contains("newer"));
+        
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_fields() {
+        let config = AppConfig {
+            name: "john.com".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(build_base_text(&config).unwrap(), "john.com,john@gmail.com,john.com");
+        
+        let deduped = AppConfig { dedup_fields: true, ..config.clone() };
+        assert_eq!(build_base_text(&deduped).unwrap(), "john.com,john@gmail.com");
+        assert_ne!(generate_password_hash(&deduped).unwrap(), generate_password_hash(&config).unwrap());
+        assert!(AppConfig::from_toml(&deduped.to_toml()).unwrap().dedup_fields);
+    }
+
+    #[test]
+    fn test_no_transforms() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-no-transforms-{}.md", std::process::id()));
+        let config = AppConfig {
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            output_encoding: "base64".to_string(),
+            prefix: "x-".to_string(),
+            no_transforms: true,
+            output_files: vec![save_path.to_string_lossy().to_string()],
+            ..Default::default()
+        };
+        
+        let result = generate_password_hash(&config).unwrap();
+        assert_eq!(result_get_password(&result, &config), raw_digest(&config).unwrap());
+        
+        // Still goes through the normal save pipeline
+        assert!(save_result(&config, &result).iter().all(|(_, outcome)| outcome.is_ok()));
+        assert_eq!(latest_saved_password(save_path.to_str().unwrap(), "john", "john.com").as_deref(), Some(raw_digest(&config).unwrap().as_str()));
+        
+        fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn test_batch_flush_every() {
+        let base = std::env::temp_dir().join(format!("pass-craft-flush-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        let configs: Vec<AppConfig> = (0..7)
+            .map(|index| AppConfig { name: format!("user{}", index), site: "john.com".to_string(), ..Default::default() })
+            .collect();
+        
+        let contents: Vec<String> = [1, 3, 100].iter()
+            .map(|&flush_every| {
+                let save_path = base.join(format!("flush-{}.md", flush_every));
+                let options = BatchOptions {
+                    save_paths: vec![save_path.to_string_lossy().to_string()],
+                    flush_every,
+                    ..Default::default()
+                };
+                run_batch(&configs, &options).unwrap();
+                fs::read_to_string(&save_path).unwrap()
+            })
+            .collect();
+        assert_eq!(contents[0].lines().count(), 7);
+        assert_eq!(contents[1], contents[0]);
+        assert_eq!(contents[2], contents[0]);
+        
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_validation_names_sources() {
+        // CLI sets upper-start past the default cut length
+        let args = CliArgs::parse_from(["pass-craft", "--hash", "method:sha256,upper-start:10"]);
+        let config = AppConfig::from_args(&args).unwrap();
+        assert_eq!(config.source_of("upper_start"), ConfigSource::Cli);
+        assert_eq!(config.source_of("cut_length"), ConfigSource::Default);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("10 (from cli)") && err.contains("8 (from default)"), "{}", err);
+        
+        // A config file sets upper-start, the command line sets the cut
+        let input_path = std::env::temp_dir().join(format!("pass-craft-provenance-{}.md", std::process::id()));
+        fs::write(&input_path, "name:john,site:john.com;method:sha512,upper-start:10\n").unwrap();
+        let mut config = AppConfig::load_from_file(input_path.to_str().unwrap(), None, &[]).unwrap();
+        let args = CliArgs::parse_from(["pass-craft", "--cut", "8"]);
+        AppConfig::apply_hash_flags(&mut config, &args);
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("10 (from file)") && err.contains("8 (from cli)"), "{}", err);
+        
+        fs::remove_file(&input_path).unwrap();
+    }
+
+    #[test]
+    fn test_only_if_absent() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-absent-{}.md", std::process::id()));
+        let save_file = save_path.to_string_lossy().to_string();
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "john.com".to_string(),
+            output_files: vec![save_file.clone()],
+            only_if_absent: true,
+            ..Default::default()
+        };
+        let result = generate_password_hash(&config).unwrap();
+        
+        // Rerunning doesn't add a duplicate
+        for _ in 0..2 {
+            assert!(save_result(&config, &result).iter().all(|(_, outcome)| outcome.is_ok()));
+        }
+        assert_eq!(count_saved_entries(&save_file, "john", "john.com"), 1);
+        
+        // Other sites are still saved
+        let other = AppConfig { site: "jane.com".to_string(), ..config.clone() };
+        save_result(&other, &generate_password_hash(&other).unwrap());
+        assert_eq!(count_saved_entries(&save_file, "john", "jane.com"), 1);
+        
+        fs::remove_file(&save_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_random_nonce() {
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,email:john@gmail.com,site:john.com", "--random-nonce"]);
+        let first = AppConfig::from_args(&args).unwrap();
+        let second = AppConfig::from_args(&args).unwrap();
+        let password = generate_password_hash(&first).unwrap();
+        assert_eq!(first.nonce.as_ref().unwrap().len(), 32);
+        assert_ne!(password, generate_password_hash(&second).unwrap());
+        
+        // The printed nonce recomputes the same password
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,email:john@gmail.com,site:john.com",
+                                        "--nonce", first.nonce.as_deref().unwrap()]);
+        assert_eq!(generate_password_hash(&AppConfig::from_args(&args).unwrap()).unwrap(), password);
+    }
+
+    #[test]
+    fn test_multi_char_end() {
+        let config = AppConfig { end_char: "!!".to_string(), ..Default::default() };
+        assert_eq!(config.warnings(), vec!["End character '!!' has more than one character, only '!' is used".to_string()]);
+        assert!(config.validate().is_ok());
+        assert!(AppConfig { strict: true, ..config.clone() }.validate().is_err());
+        
+        // Output is unchanged: only the first character is used
+        let single = AppConfig { end_char: "!".to_string(), ..config.clone() };
+        assert_eq!(generate_password_hash(&config).unwrap(), generate_password_hash(&single).unwrap());
+        assert!(AppConfig { end_char: "é".to_string(), ..Default::default() }.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_explain_diff() {
+        let parse = |sslf: &str| {
+            let mut config = AppConfig::default();
+            AppConfig::apply_sslf_config(&mut config, sslf).unwrap();
+            config
+        };
+        let base = parse("name:john,email:john@gmail.com,site:john.com;method:sha512,cut:8");
+        
+        // cut only changes formatting
+        let diffs = explain_diff(&base, &parse("name:john,email:john@gmail.com,site:john.com;method:sha512,cut:12"));
+        assert_eq!(diffs, vec![FieldDiff { field: "cut_length".to_string(), a: "8".to_string(), b: "12".to_string(), hash_input: false }]);
+        
+        // name changes the hashed text
+        let diffs = explain_diff(&base, &parse("name:jane,email:john@gmail.com,site:john.com;method:sha512,cut:8"));
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, "name");
+        assert!(diffs[0].hash_input);
+        
+        assert!(explain_diff(&base, &base).is_empty());
+        
+        // Argon2id costs change the digest
+        let argon2 = parse("name:john,site:john.com;method:argon2id,salt:somesaltsalt");
+        let diffs = explain_diff(&argon2, &parse("name:john,site:john.com;method:argon2id,salt:somesaltsalt,memory:64"));
+        assert_eq!(diffs, vec![FieldDiff { field: "argon2_memory".to_string(), a: "19456".to_string(), b: "64".to_string(), hash_input: true }]);
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let input_path = std::env::temp_dir().join(format!("pass-craft-hash-file-{}.bin", std::process::id()));
+        let input_path = input_path.to_str().unwrap();
+        
+        // Empty input: well-known digest plus a warning, error under strict
+        fs::write(input_path, b"").unwrap();
+        let (digest, warnings) = hash_file(input_path, "sha256", &AppConfig::default(), false).unwrap();
+        assert_eq!(digest, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(warnings.len(), 1);
+        assert!(hash_file(input_path, "sha256", &AppConfig::default(), true).is_err());
+        
+        // Streaming across chunk boundaries matches hashing the whole string
+        let text = "john,john@gmail.com,john.com".repeat(5000);
+        fs::write(input_path, &text).unwrap();
+        for method in ["MD5", "SHA1", "SHA256", "SHA512"] {
+            let (digest, warnings) = hash_file(input_path, method, &AppConfig::default(), true).unwrap();
+            assert_eq!(digest, get_string_hash(&text, method).unwrap());
+            assert!(warnings.is_empty());
+        }
+        
+        fs::remove_file(input_path).unwrap();
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        let with_pipeline = |pipeline: &str| AppConfig {
+            pipeline: pipeline.split(',').map(|transform| transform.to_string()).collect(),
+            ..config.clone()
+        };
+        
+        // The default pipeline reproduces the fixed order
+        assert_eq!(generate_password_hash(&with_pipeline("truncate,endchar,case")).unwrap(), "john,B5Cb304!,john.com");
+        assert_eq!(generate_password_hash(&config).unwrap(), "john,B5Cb304!,john.com");
+        
+        // End character before truncation is cut off again
+        let reordered = with_pipeline("endchar,truncate,case");
+        assert_eq!(generate_password_hash(&reordered).unwrap(), "john,B5Cb3043,john.com");
+        assert_eq!(generate_password_hash(&reordered).unwrap(), generate_password_hash(&reordered).unwrap());
+        
+        // Selecting a subset skips the rest
+        assert_eq!(generate_password_hash(&with_pipeline("truncate")).unwrap(), "john,b5cb3043,john.com");
+        
+        // Unknown transforms are rejected
+        assert!(with_pipeline("truncate,charset").validate().is_err());
+        assert!(generate_password_hash(&with_pipeline("truncate,charset")).is_err());
+        assert!(AppConfig { truncate_unit: "bytes".to_string(), ..with_pipeline("case,truncate") }.validate().is_err());
+    }
+
+    #[test]
+    fn test_path_helpers_separators() {
+        let sep = std::path::MAIN_SEPARATOR;
+        
+        // Forward slash, backslash and mixed separators
+        assert_eq!(path_get_name("a/b/c.txt"), "c.txt");
+        assert_eq!(path_get_name("a\\b\\c.txt"), "c.txt");
+        assert_eq!(path_get_name("a/b\\c.txt"), "c.txt");
+        assert_eq!(path_get_name(&format!("a{}b{}c.txt", sep, sep)), "c.txt");
+        assert_eq!(path_get_name("c.txt"), "c.txt");
+        assert_eq!(path_get_name("a\\b\\"), "b");
+        assert_eq!(path_get_name(".."), "");
+        
+        assert_eq!(path_get_dirs("a/b/c.txt"), "a/b");
+        assert_eq!(path_get_dirs("a\\b\\c.txt"), "a\\b");
+        assert_eq!(path_get_dirs("a\\b/c.txt"), "a\\b");
+        assert_eq!(path_get_dirs(&format!("a{}b{}c.txt", sep, sep)), format!("a{}b", sep));
+        assert_eq!(path_get_dirs("/c.txt"), "/");
+        assert_eq!(path_get_dirs("C:\\c.txt"), "C:");
+        assert_eq!(path_get_dirs("c.txt"), "");
+        
+        assert_eq!(path_normalize("a\\b/c.txt", "\\", "/"), "a/b/c.txt");
+        assert_eq!(path_normalize("a/b/c.txt", "/", "\\"), "a\\b\\c.txt");
+        assert_eq!(path_normalize("a/b", "/", &sep.to_string()), format!("a{}b", sep));
+    }
+
+    #[test]
+    fn test_canonicalize_site() {
+        let password = |site: &str, canonicalize_site| {
+            let config = AppConfig {
+                name: "john".to_string(),
+                email: "john@gmail.com".to_string(),
+                site: site.to_string(),
+                canonicalize_site,
+                ..Default::default()
+            };
+            result_get_password(&generate_password_hash(&config).unwrap(), &config)
+        };
+        
+        let bare = password("example.com", true);
+        for site in ["example.com/", "https://example.com", "http://www.example.com/", "www.example.com"] {
+            assert_eq!(password(site, true), bare, "{}", site);
+        }
+        assert_eq!(password("example.com", false), bare);
+        
+        // Off by default: URL forms still hash differently
+        assert_ne!(password("https://example.com", false), bare);
+        assert_eq!(canonical_site("https://example.com/login/"), "example.com/login");
+        
+        let base = AppConfig::default();
+        let diff = explain_diff(&base, &AppConfig { canonicalize_site: true, ..base.clone() });
+        assert!(diff.iter().any(|d| d.field == "canonicalize_site" && d.hash_input));
+    }
+
+    #[test]
+    fn test_env_assignment() {
+        assert_eq!(env_assignment("MYPW", "B5Cb304!").unwrap(), "export MYPW='B5Cb304!'");
+        assert_eq!(env_assignment("MYPW", "it's").unwrap(), "export MYPW='it'\\''s'");
+        assert_eq!(env_assignment("_PW2", "$(x) `y` \"z\"").unwrap(), "export _PW2='$(x) `y` \"z\"'");
+        assert!(env_assignment("2PW", "x").is_err());
+        assert!(env_assignment("MY-PW", "x").is_err());
+    }
+
+    #[test]
+    fn test_registry_json() {
+        let json = registry_json();
+        assert!(json.starts_with('[') && json.ends_with(']'));
+        for algorithm in &ALGORITHMS {
+            assert!(json.contains(&algorithm.to_json()));
+        }
+        assert!(json.contains("{\"name\": \"SHA256\", \"output_bytes\": 32, \"weak\": false, \"crypto\": true, \"xof\": false, \"accepts_salt\": false, \"accepts_key\": false, \"accepts_cost\": false}"));
+        assert_eq!(json.matches("\"name\"").count(), ALGORITHMS.len());
+    }
+
+    #[test]
+    fn test_unambiguous_fields() {
+        let split_a = AppConfig { name: "john".to_string(), email: "x".to_string(), site: "a,b".to_string(), ..Default::default() };
+        let split_b = AppConfig { name: "john".to_string(), email: "x,a".to_string(), site: "b".to_string(), ..Default::default() };
+        
+        // Naive base text collides
+        assert_eq!(build_base_text(&split_a).unwrap(), build_base_text(&split_b).unwrap());
+        assert_eq!(raw_digest(&split_a).unwrap(), raw_digest(&split_b).unwrap());
+        
+        let split_a = AppConfig { unambiguous_fields: true, ..split_a };
+        let split_b = AppConfig { unambiguous_fields: true, ..split_b };
+        assert_eq!(build_base_text(&split_a).unwrap(), "4:john,1:x,3:a,b");
+        assert_ne!(build_base_text(&split_a).unwrap(), build_base_text(&split_b).unwrap());
+        assert_ne!(raw_digest(&split_a).unwrap(), raw_digest(&split_b).unwrap());
+        
+        let base = AppConfig::default();
+        let diff = explain_diff(&base, &AppConfig { unambiguous_fields: true, ..base.clone() });
+        assert!(diff.iter().any(|d| d.field == "unambiguous_fields" && d.hash_input));
+    }
+
+    #[test]
+    fn test_auto_counter_past_max_entries() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-rotate-{}.md", std::process::id()));
+        let save_file = save_path.to_string_lossy().to_string();
+        let archive_file = format!("{}.archive", save_file);
+        let _ = fs::remove_file(&save_path);
+        let _ = fs::remove_file(&archive_file);
+        
+        // Archived rotations still count, so no password is issued twice
+        let mut passwords = Vec::new();
+        for expected in 0..4 {
+            let counter = next_auto_counter(&save_file, "john", "example.com");
+            assert_eq!(counter, expected);
+            let config = AppConfig {
+                name: "john".to_string(),
+                site: "example.com".to_string(),
+                output_files: vec![save_file.clone()],
+                max_entries: Some(2),
+                counter: Some(counter),
+                ..Default::default()
+            };
+            let result = generate_password_hash(&config).unwrap();
+            save_result(&config, &result);
+            passwords.push(result);
+        }
+        assert_eq!(count_saved_entries(&save_file, "john", "example.com"), 2);
+        for (i, password) in passwords.iter().enumerate() {
+            assert!(!passwords[i + 1..].contains(password));
+        }
+        
+        // The recorded counters survive pruning the archive
+        fs::remove_file(&archive_file).unwrap();
+        assert_eq!(next_auto_counter(&save_file, "john", "example.com"), 4);
+        fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-max-{}.md", std::process::id()));
+        let save_file = save_path.to_string_lossy().to_string();
+        let archive_file = format!("{}.archive", save_file);
+        fs::write(&save_path, "name:john,site:a.com;method:sha512").unwrap();
+        
+        for site in ["a.com", "b.com", "c.com"] {
+            let config = AppConfig {
+                name: "john".to_string(),
+                site: site.to_string(),
+                output_files: vec![save_file.clone()],
+                max_entries: Some(2),
+                ..Default::default()
+            };
+            save_result(&config, &generate_password_hash(&config).unwrap());
+        }
+        
+        // Oldest entry moved to the archive, the config line stays
+        let content = fs::read_to_string(&save_path).unwrap();
+        assert!(content.starts_with("name:john,site:a.com;method:sha512"));
+        assert_eq!(count_saved_entries(&save_file, "john", "a.com"), 0);
+        assert_eq!(count_saved_entries(&save_file, "john", "b.com"), 1);
+        assert_eq!(count_saved_entries(&save_file, "john", "c.com"), 1);
+        assert_eq!(count_saved_entries(&archive_file, "john", "a.com"), 1);
+        
+        fs::remove_file(&save_path).unwrap();
+        fs::remove_file(&archive_file).unwrap();
+    }
+
+    #[test]
+    fn test_check_clock() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-clock-{}.md", std::process::id()));
+        let save_file = save_path.to_str().unwrap();
+        fs::write(&save_path, [
+            "name:john,site:john.com;method:sha512",
+            "<!-- john,a!,john.com;time:2026-01-01 10:00:00 -->",
+            "<!-- john,b!,john.com;time:2099-01-01 10:00:00 -->",
+            "<!-- john,c!,john.com;time:2026-02-01 10:00:00 -->",
+            "<!-- john,d!,john.com -->",
+        ].join("\n")).unwrap();
+        
+        let issues = check_clock(save_file, "2026-10-15 00:00:00").unwrap();
+        assert_eq!(issues, vec![
+            "Line 3: 2099-01-01 10:00:00 is in the future".to_string(),
+            "Line 4: 2026-02-01 10:00:00 is earlier than a previous entry".to_string(),
+        ]);
+        
+        // Metadata doesn't hide the entry from lookups
+        assert_eq!(latest_saved_password(save_file, "john", "john.com").as_deref(), Some("d!"));
+        assert_eq!(count_saved_entries(save_file, "john", "john.com"), 4);
+        assert_eq!(parse_saved_entry("<!-- john,a;b,john.com -->").unwrap().password, "a;b");
+        assert_eq!(parse_saved_entry("<!-- john,pw,https://a.com;version:1.0 -->").unwrap().site, "https://a.com");
+        assert_eq!(parse_saved_entry("<!-- john,pw,https://a.com -->").unwrap().site, "https://a.com");
+        assert!(parse_saved_entry("<!-- name:john,site:a.com;method:sha256 -->").is_none());
+        
+        // --save-time writes a timestamp that passes the check
+        let config = AppConfig { name: "jane".to_string(), output_files: vec![save_file.to_string()], save_time: true, ..Default::default() };
+        fs::write(&save_path, "").unwrap();
+        save_result(&config, &generate_password_hash(&config).unwrap());
+        let line = fs::read_to_string(&save_path).unwrap();
+        assert!(parse_saved_entry(line.trim()).unwrap().time.is_some());
+        assert!(check_clock(save_file, &get_time_now()).unwrap().is_empty());
+        
+        fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_sslf() {
+        let config = parse_sslf("name:john,email:john@gmail.com,site:john.com;method:sha256,cut:12,end:+,upper-start:5").unwrap();
+        assert_eq!((config.name.as_str(), config.email.as_str(), config.site.as_str()), ("john", "john@gmail.com", "john.com"));
+        assert_eq!((config.method.as_str(), config.cut_length, config.end_char.as_str(), config.upper_start), ("sha256", 12, "+", 5));
+        
+        // Missing tail keeps the default hash settings
+        let config = parse_sslf("name:john,site:john.com").unwrap();
+        let defaults = AppConfig::default();
+        assert_eq!((config.method, config.cut_length), (defaults.method, defaults.cut_length));
+        
+        // Malformed tokens and invalid settings are errors
+        assert!(parse_sslf("name:john;cutx").is_err());
+        assert!(parse_sslf("name:john;method:sha3").is_err());
+    }
+
+    #[test]
+    fn test_verify_determinism() {
+        let config = AppConfig { name: "john".to_string(), site: "john.com".to_string(), ..Default::default() };
+        assert_eq!(verify_determinism(&config, 5).unwrap(), generate_password_hash(&config).unwrap());
+        
+        // A resolved nonce is part of the config, so it stays deterministic
+        let nonce = AppConfig { nonce: Some("00ff".to_string()), ..config.clone() };
+        assert!(verify_determinism(&nonce, 5).is_ok());
+        
+        // An ungated random element is caught
+        let mut run = 0;
+        let err = verify_determinism_with(3, || {
+            run += 1;
+            let config = AppConfig { nonce: Some(run.to_string()), ..config.clone() };
+            generate_password_hash(&config)
+        }).unwrap_err();
+        assert!(err.to_string().starts_with("Run 2 produced"), "{}", err);
+    }
+
+    #[test]
+    fn test_field_files() {
+        let site_path = std::env::temp_dir().join(format!("pass-craft-site-{}.txt", std::process::id()));
+        let site_file = site_path.to_str().unwrap();
+        fs::write(site_file, "# long site value\n\n  john.com  \r\n").unwrap();
+        
+        let inline = CliArgs::parse_from(["pass-craft", "--text", "name:john,email:john@gmail.com,site:john.com"]);
+        let from_file = CliArgs::parse_from(["pass-craft", "--text", "name:john,email:john@gmail.com,site:other.com", "--site-file", site_file]);
+        let from_file = AppConfig::from_args(&from_file).unwrap();
+        assert_eq!(from_file.site, "john.com");
+        assert_eq!(generate_password_hash(&from_file).unwrap(), generate_password_hash(&AppConfig::from_args(&inline).unwrap()).unwrap());
+        
+        fs::write(site_file, "# only a comment\n").unwrap();
+        assert!(read_field_file(site_file, false).is_err());
+        
+        fs::remove_file(site_file).unwrap();
+    }
+
+    #[test]
+    fn test_shake() {
+        // FIPS 202 known answers
+        assert_eq!(get_string_hash("", "SHAKE128").unwrap(), "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26");
+        assert_eq!(get_string_hash("", "SHAKE256").unwrap(),
+                   "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be");
+        
+        // Incremental absorb across the rate matches one-shot
+        let text = "john,john@gmail.com,john.com".repeat(20);
+        let mut builder = HashBuilder::new("SHAKE256").unwrap();
+        for chunk in text.as_bytes().chunks(7) {
+            builder.update(chunk);
+        }
+        assert_eq!(hex_encode(&builder.digest().unwrap())[..16], *"a36b17523ca33601");
+        
+        // A full 168-byte SHAKE128 block in, then squeezing past the first output block
+        let input: String = (0..168).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        let long = AppConfig { method: "SHAKE128".to_string(), cut_length: 400, ..Default::default() };
+        let digest = get_config_digest(&input, &long).unwrap();
+        assert_eq!(digest.len(), 200);
+        assert_eq!(hex_encode(&digest[168..]), "e04f20a8a4f4a2afef79bca551988db341779a123480635051c27a930af7bf40");
+        
+        // Output length follows cut_length: 12 hex characters squeeze 6 bytes
+        let config = AppConfig {
+            method: "SHAKE256".to_string(),
+            cut_length: 12,
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let digest = get_config_digest(&build_base_text(&config).unwrap(), &config).unwrap();
+        assert_eq!(digest.len(), 6);
+        assert_eq!(hex_encode(&digest), get_string_hash(&build_base_text(&config).unwrap(), "SHAKE256").unwrap()[..12]);
+        assert_eq!(generate_password_hash(&config).unwrap().len(), "john,,john.com".len() + 12);
+    }
+
+    #[test]
+    fn test_hash_whole_file() {
+        let input_path = std::env::temp_dir().join(format!("pass-craft-whole-{}.txt", std::process::id()));
+        let input_file = input_path.to_str().unwrap();
+        let generate = || {
+            let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,site:john.com", "--file", input_file, "--hash-whole-file"]);
+            let config = AppConfig::from_args(&args).unwrap();
+            (config.name.clone(), config.site.clone(), generate_password_hash(&config).unwrap())
+        };
+        
+        fs::write(&input_path, "name:jane,site:jane.com;method:md5\nsecond line\n").unwrap();
+        let (name, site, first) = generate();
+        assert_eq!((name.as_str(), site.as_str()), ("john", "john.com"));
+        assert!(first.starts_with("john,") && first.ends_with(",john.com"));
+        
+        // One changed byte changes the password
+        fs::write(&input_path, "name:jane,site:jane.com;method:md5\nsecond linf\n").unwrap();
+        assert_ne!(generate().2, first);
+        
+        fs::remove_file(&input_path).unwrap();
+    }
+
+    #[test]
+    fn test_output_encoding_validation() {
+        let mut config = AppConfig::default();
+        AppConfig::apply_hash_config(&mut config, "encoding:bas64").unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(err, "Unknown output encoding 'bas64', expected one of: hex, base64, zbase32, emoji");
+        
+        AppConfig::apply_hash_config(&mut config, "encoding:BASE64").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_generation_result() {
+        let config = AppConfig {
+            name: "john".to_string(),
+            email: "john@gmail.com".to_string(),
+            site: "john.com".to_string(),
+            ..Default::default()
+        };
+        
+        let result = generate(&config).unwrap();
+        assert_eq!(result.name, "john");
+        assert_eq!(result.site, "john.com");
+        assert_eq!(result.password, "B5Cb304!");
+        assert_eq!(result.method, "SHA512");
+        assert!(chrono::NaiveDateTime::parse_from_str(&result.generated_at, "%Y-%m-%d %H:%M:%S").is_ok());
+        
+        // The flat string is a formatter over the struct
+        assert_eq!(result.to_string(), generate_password_hash(&config).unwrap());
+    }
+
+    #[test]
+    fn test_comment_prefixes() {
+        let prefixes: Vec<String> = vec!["#".into(), ";".into(), "//".into()];
+        assert_eq!(strip_line_comment("; old site", &prefixes), "");
+        assert_eq!(strip_line_comment("  // note", &prefixes), "");
+        assert_eq!(strip_line_comment("site:https://a.com;cut:8 ; inline", &prefixes), "site:https://a.com;cut:8");
+
+        let dir = std::env::temp_dir().join(format!("pass-craft-comments-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("config.txt");
+        fs::write(&file, "; generated config\nname:john,email:john@gmail.com,site:john.com;cut:12 ; keep it short\n; trailing note\n").unwrap();
+        let lines = sslf_load_file(file.to_str().unwrap(), "", &parse_comment_prefixes("#,;,//"));
+        assert_eq!(lines, vec!["name:john,email:john@gmail.com,site:john.com;cut:12".to_string()]);
+        let config = parse_sslf(&lines[0]).unwrap();
+        assert_eq!(config.cut_length, 12);
+
+        // Without --comment-prefixes only a leading # comments, an inline # is data
+        fs::write(&file, "# generated config\nname:john #1,site:a.com;method:sha256,cut:12\n").unwrap();
+        let lines = sslf_load_file(file.to_str().unwrap(), "", &[]);
+        assert_eq!(lines, vec!["name:john #1,site:a.com;method:sha256,cut:12".to_string()]);
+        let config = parse_sslf(&lines[0]).unwrap();
+        assert_eq!((config.name.as_str(), config.site.as_str(), config.cut_length), ("john #1", "a.com", 12));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_also_encode() {
+        fn decode_with(alphabet: &str, text: &str, base: u32) -> Vec<u8> {
+            // Big-endian base conversion, enough for the round trip below
+            let mut bytes: Vec<u8> = Vec::new();
+            for c in text.chars() {
+                let mut carry = alphabet.find(c).unwrap() as u32;
+                for byte in bytes.iter_mut().rev() {
+                    carry += (*byte as u32) * base;
+                    *byte = (carry & 0xff) as u8;
+                    carry >>= 8;
+                }
+                while carry > 0 {
+                    bytes.insert(0, (carry & 0xff) as u8);
+                    carry >>= 8;
+                }
+            }
+            let zeros = text.chars().take_while(|&c| alphabet.starts_with(c)).count();
+            [vec![0; zeros], bytes].concat()
+        }
+        fn base64_decode(text: &str) -> Vec<u8> {
+            const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            let bits: Vec<u32> = text.trim_end_matches('=').chars().map(|c| ALPHABET.find(c).unwrap() as u32).collect();
+            bits.chunks(4)
+                .flat_map(|chunk| {
+                    let group = chunk.iter().enumerate().fold(0, |acc, (i, v)| acc | v << (18 - 6 * i));
+                    (0..chunk.len() - 1).map(move |i| (group >> (16 - 8 * i)) as u8)
+                })
+                .collect()
+        }
+
+        let config = AppConfig::default();
+        let digest = get_string_digest(&build_base_text(&config).unwrap(), &config.method).unwrap();
+        let encoded = also_encode(&config, "hex,base64,base58").unwrap();
+        let labels: Vec<&str> = encoded.iter().map(|(encoding, _)| encoding.as_str()).collect();
+        assert_eq!(labels, ["hex", "base64", "base58"]);
+        assert_eq!(encoded[0].1, raw_digest(&config).unwrap());
+        assert_eq!(base64_decode(&encoded[1].1), digest);
+        assert_eq!(decode_with("123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz", &encoded[2].1, 58), digest);
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert!(also_encode(&config, "base32").is_err());
+    }
+
+    #[test]
+    fn test_health_check() {
+        health_check().unwrap();
+        let dir = std::env::temp_dir().join(format!("pass-craft-health-{}", std::process::id()));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_inject_symbol() {
+        let config = AppConfig {
+            inject_symbol: Some("#@3".to_string()),
+            ..AppConfig::default()
+        };
+        config.validate().unwrap();
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.chars().nth(3), Some('#'));
+        assert_eq!(password.chars().count(), config.cut_length);
+        assert_eq!(generate_password_hash(&config).unwrap(), generate_password_hash(&config).unwrap());
+
+        assert_eq!(inject_symbol("abcd", "@@1", "shift").unwrap(), "a@bcd");
+        assert_eq!(inject_symbol("abcd", "-@3", "replace").unwrap(), "abc-");
+        assert!(inject_symbol("abcd", "-@4", "replace").is_err());
+        let outside = AppConfig { inject_symbol: Some("#@8".to_string()), ..AppConfig::default() };
+        assert!(outside.validate().is_err());
+    }
+
+    #[test]
+    fn test_atomic_multi_save() {
+        let dir = std::env::temp_dir().join(format!("pass-craft-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.md").to_string_lossy().to_string();
+        let bad = dir.join("missing").join("bad.md").to_string_lossy().to_string();
+        fs::write(&good, "<!-- old,pw,site -->").unwrap();
+
+        let config = AppConfig {
+            output_files: vec![good.clone(), bad.clone()],
+            atomic_multi_save: true,
+            ..AppConfig::default()
+        };
+        let outcomes = save_result(&config, "john,pw,john.com");
+        assert!(outcomes.iter().all(|(_, outcome)| outcome.is_err()));
+        assert_eq!(fs::read_to_string(&good).unwrap(), "<!-- old,pw,site -->");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let config = AppConfig { output_files: vec![good.clone()], ..config };
+        assert!(save_result(&config, "john,pw,john.com").iter().all(|(_, outcome)| outcome.is_ok()));
+        assert!(fs::read_to_string(&good).unwrap().ends_with(&saved_entry_line("john,pw,john.com", None, None)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bom_config() {
+        let base = std::env::temp_dir().join(format!("pass-craft-bom-{}", std::process::id()));
+        let line = "name:john,email:john@gmail.com,site:john.com;cut:10";
+        let utf16 = |bom: [u8; 2], to_bytes: fn(u16) -> [u8; 2]| -> Vec<u8> {
+            bom.into_iter().chain(line.encode_utf16().flat_map(to_bytes)).collect()
+        };
+        let variants = [
+            line.as_bytes().to_vec(),
+            [b"\xef\xbb\xbf".as_slice(), line.as_bytes()].concat(),
+            utf16([0xff, 0xfe], u16::to_le_bytes),
+            utf16([0xfe, 0xff], u16::to_be_bytes),
+        ];
+        let configs: Vec<AppConfig> = variants.iter()
+            .map(|bytes| {
+                fs::write(&base, bytes).unwrap();
+                AppConfig::load_from_file(base.to_str().unwrap(), None, &[]).unwrap()
+            })
+            .collect();
+        assert_eq!(configs[0].name, "john");
+        assert!(configs.iter().all(|config| *config == configs[0]));
+        assert_eq!(generate_password_hash(&configs[1]).unwrap(), generate_password_hash(&configs[0]).unwrap());
+
+        fs::write(&base, [0xff, 0xfe, b'a']).unwrap();
+        assert!(read_text_file(base.to_str().unwrap(), false).is_err());
+        fs::remove_file(&base).unwrap();
+    }
+
+    #[test]
+    fn test_since_last_run() {
+        let dir = std::env::temp_dir().join(format!("pass-craft-since-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let state_path = std::env::temp_dir().join(format!("pass-craft-since-{}.state", std::process::id()));
+        let state_path = state_path.to_str().unwrap();
+        fs::write(dir.join("a.txt"), "name:a,email:a@a.com,site:a.com").unwrap();
+        fs::write(dir.join("b.txt"), "name:b,email:b@b.com,site:b.com").unwrap();
+        let files = batch_input_files(dir.to_str().unwrap()).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let (modified, state) = modified_since_last_run(&files, &load_run_state(state_path)).unwrap();
+        assert_eq!(modified, files);
+        save_run_state(state_path, &state).unwrap();
+
+        let (modified, _) = modified_since_last_run(&files, &load_run_state(state_path)).unwrap();
+        assert!(modified.is_empty());
+
+        let later = std::time::SystemTime::now() + Duration::from_secs(60);
+        fs::File::options().write(true).open(&files[1]).unwrap().set_modified(later).unwrap();
+        let (modified, _) = modified_since_last_run(&files, &load_run_state(state_path)).unwrap();
+        assert_eq!(modified, vec![files[1].clone()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(state_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fifo_config() {
+        let fifo = std::env::temp_dir().join(format!("pass-craft-fifo-{}", std::process::id()));
+        let status = std::process::Command::new("mkfifo").arg(&fifo).status().unwrap();
+        assert!(status.success());
+        assert!(is_stream(fifo.to_str().unwrap()));
+
+        let writer_path = fifo.clone();
+        let writer = std::thread::spawn(move || {
+            fs::write(writer_path, "name:john,email:john@gmail.com,site:john.com;cut:10\n").unwrap();
+        });
+        // Loading reads the file twice (save file check, then parse), the pipe only once
+        let config = AppConfig::load_from_file(fifo.to_str().unwrap(), None, &[]).unwrap();
+        writer.join().unwrap();
+        assert_eq!(config.name, "john");
+        assert_eq!(config.cut_length, 10);
+        fs::remove_file(&fifo).unwrap();
+    }
+
+    #[test]
+    fn test_check_digit() {
+        let config = AppConfig { check_digit: true, ..AppConfig::default() };
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.len(), config.cut_length + 1);
+        assert!(verify_check_digit(&password));
+
+        // Every single-character substitution is caught
+        for (index, original) in password.char_indices().take(config.cut_length) {
+            for typo in ['0', 'a', 'Z', '!'].into_iter().filter(|&typo| typo != original) {
+                let mut corrupted = password.clone();
+                corrupted.replace_range(index..index + 1, &typo.to_string());
+                assert!(!verify_check_digit(&corrupted), "{} not caught", corrupted);
+            }
+        }
+        assert!(!verify_check_digit(""));
+    }
+
+    #[test]
+    fn test_hash_builder() {
+        for algorithm in ALGORITHMS {
+            let salted = AppConfig { salt: Some("pass-craft".to_string()), argon2_memory: 64, ..AppConfig::default() };
+            let config = AppConfig { method: algorithm.name.to_string(), ..salted };
+            let base_text = build_base_text(&config).unwrap();
+
+            let mut builder = HashBuilder::with_config(&config).unwrap();
+            for chunk in base_text.as_bytes().chunks(3) {
+                builder.update(chunk);
+            }
+            let chunked = builder.finalize(&config).unwrap();
+            assert_eq!(chunked, result_get_password(&generate_password_hash(&config).unwrap(), &config));
+
+            let mut builder = HashBuilder::with_config(&config).unwrap();
+            builder.update(base_text.as_bytes());
+            assert_eq!(hex_encode(&builder.digest().unwrap()), raw_digest(&config).unwrap());
+        }
+        assert!(HashBuilder::new("CRC32").is_err());
+    }
+
+    #[test]
+    fn test_field_methods() {
+        let base = AppConfig {
+            field_methods: parse_field_methods("name:sha256,site:sha1").unwrap(),
+            ..AppConfig::default()
+        };
+        base.validate().unwrap();
+        assert_eq!(base.field_method("name"), "SHA256");
+        assert_eq!(base.field_method("email"), base.method);
+        let base_text = build_base_text(&base).unwrap();
+        let parts: Vec<&str> = base_text.split(',').collect();
+        assert_eq!(parts[0], get_string_hash(&base.name, "SHA256").unwrap());
+        assert_eq!(parts[2], get_string_hash(&base.site, "SHA1").unwrap());
+
+        // Switching the site method only changes the site digest
+        let changed = AppConfig { field_methods: parse_field_methods("name:sha256,site:md5").unwrap(), ..base.clone() };
+        let changed_text = build_base_text(&changed).unwrap();
+        let changed_parts: Vec<&str> = changed_text.split(',').collect();
+        assert_eq!(changed_parts[..2], parts[..2]);
+        assert_ne!(changed_parts[2], parts[2]);
+        assert_ne!(generate_password_hash(&changed).unwrap(), generate_password_hash(&base).unwrap());
+
+        assert_eq!(AppConfig::from_toml(&base.to_toml()).unwrap().field_methods, base.field_methods);
+        let unknown = AppConfig { field_methods: vec![("phone".into(), "SHA1".into())], ..AppConfig::default() };
+        assert!(unknown.validate().is_err());
+    }
+
+    #[test]
+    fn test_wrap_display() {
+        let config = AppConfig { cut_length: 32, ..AppConfig::default() };
+        let result = generate_password_hash(&config).unwrap();
+        let displayed = wrap_display(&result, 10);
+        assert!(displayed.lines().all(|line| line.chars().count() <= 10));
+        assert_eq!(displayed.lines().next().unwrap().chars().count(), 10);
+        assert_eq!(displayed.replace('\n', ""), result);
+        assert_eq!(wrap_display(&result, 0), result);
+        assert_eq!(wrap_display("🐶🐱🐭", 2), "🐶🐱\n🐭");
+    }
+
+    #[test]
+    fn test_saved_version() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-version-{}.md", std::process::id()));
+        let save_file = save_path.to_str().unwrap();
+        let config = AppConfig { output_files: vec![save_file.to_string()], save_time: true, ..Default::default() };
+        save_result(&config, "john,pw!,john.com");
+        let content = fs::read_to_string(save_file).unwrap();
+        let entry = parse_saved_entry(content.lines().last().unwrap()).unwrap();
+        assert_eq!(entry.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(entry.password, "pw!");
+        assert!(entry.time.is_some());
+        assert!(verify_file(save_file, env!("CARGO_PKG_VERSION")).unwrap().is_empty());
+
+        // An entry from another version is flagged, unversioned entries are left alone
+        fs::write(save_file, "<!-- john,pw!,john.com;version:0.1.0 -->\n<!-- jane,pw?,jane.com -->\n").unwrap();
+        let warnings = verify_file(save_file, env!("CARGO_PKG_VERSION")).unwrap();
+        assert_eq!(warnings, vec![format!("Line 1: john,john.com was saved by pass-craft 0.1.0, running {}", env!("CARGO_PKG_VERSION"))]);
+        fs::remove_file(save_file).unwrap();
+    }
+
+    #[test]
+    fn test_domain_tag() {
+        let untagged = AppConfig::default();
+        let tagged = AppConfig { domain: Some("pass-craft".to_string()), ..AppConfig::default() };
+        let other = AppConfig { domain: Some("other-tool".to_string()), ..AppConfig::default() };
+        tagged.validate().unwrap();
+        assert_eq!(build_base_text(&tagged).unwrap(), format!("pass-craft\n{}", build_base_text(&untagged).unwrap()));
+
+        let passwords: Vec<String> = [&untagged, &tagged, &other].iter()
+            .map(|config| generate_password_hash(config).unwrap())
+            .collect();
+        assert_ne!(passwords[0], passwords[1]);
+        assert_ne!(passwords[1], passwords[2]);
+        assert_eq!(AppConfig::from_toml(&other.to_toml()).unwrap().domain, other.domain);
+        assert!(AppConfig { domain: Some("a\nb".to_string()), ..AppConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_prune_archived() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-prune-{}.md", std::process::id()));
+        let save_file = save_path.to_str().unwrap();
+        let archive = format!("{}.archive", save_file);
+        let live = "<!-- john,live!,john.com;time:2020-01-01 10:00:00 -->";
+        fs::write(save_file, live).unwrap();
+        fs::write(&archive, [
+            "<!-- john,old!,john.com;time:2026-01-01 10:00:00 -->",
+            "<!-- john,recent!,john.com;time:2026-06-20 10:00:00 -->",
+            "<!-- john,undated!,john.com -->",
+            "<!-- jane,older!,jane.com;time:2025-03-01 10:00:00,version:0.3.0 -->",
+        ].join("\n")).unwrap();
+
+        assert_eq!(prune_archived(save_file, 30, "2026-07-01 10:00:00").unwrap(), 2);
+        let passwords: Vec<String> = fs::read_to_string(&archive).unwrap()
+            .lines()
+            .filter_map(parse_saved_entry)
+            .map(|entry| entry.password)
+            .collect();
+        assert_eq!(passwords, ["recent!", "undated!"]);
+        assert_eq!(fs::read_to_string(save_file).unwrap(), live);
+        fs::remove_file(save_file).unwrap();
+        fs::remove_file(&archive).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_site_case() {
+        let password = |site: &str, normalize_site_case| {
+            let config = AppConfig { site: site.to_string(), normalize_site_case, ..AppConfig::default() };
+            generate_password_hash(&config).map(|result| result_get_password(&result, &config)).unwrap()
+        };
+        assert_eq!(password("Example.COM", true), password("example.com", true));
+        assert_eq!(password("Example.COM", true), password("example.com", false));
+        assert_ne!(password("Example.COM", false), password("example.com", false));
+
+        assert_eq!(lowercase_site_domain("HTTPS://Example.COM/Docs/Page"), "https://example.com/Docs/Page");
+        assert_eq!(lowercase_site_domain("Example.COM/Path"), "example.com/Path");
+        assert_ne!(password("example.com/A", true), password("example.com/a", true));
+
+        let base = AppConfig::default();
+        let diff = explain_diff(&base, &AppConfig { normalize_site_case: true, ..base.clone() });
+        assert!(diff.iter().any(|d| d.field == "normalize_site_case" && d.hash_input));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_server() {
+        use std::io::{BufRead, Write};
+        use std::os::unix::fs::PermissionsExt;
+        let socket = std::env::temp_dir().join(format!("pass-craft-server-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&socket);
+        let listener = bind_private_socket(socket.to_str().unwrap()).unwrap();
+        assert_eq!(fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(bind_private_socket(socket.to_str().unwrap()).is_err());
+        std::thread::spawn(|| run_server(listener, "secret"));
+
+        let sslf = "name:john,email:john@gmail.com,site:john.com;method:sha256,cut:12";
+        let expected = generate(&parse_sslf(sslf).unwrap()).unwrap().password;
+        let mut stream = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+        writeln!(stream, "secret {}", sslf).unwrap();
+        writeln!(stream, "wrong {}", sslf).unwrap();
+        let mut replies = std::io::BufReader::new(&stream).lines();
+        assert_eq!(replies.next().unwrap().unwrap(), expected);
+        assert_eq!(replies.next().unwrap().unwrap(), "error: bad token");
+        fs::remove_file(&socket).unwrap();
+    }
+
+    #[test]
+    fn test_explain_entropy() {
+        let config = AppConfig { name: "john".into(), email: "john@gmail.com".into(), site: "john.com".into(), ..AppConfig::default() };
+        let breakdown = explain_entropy(&config);
+        assert_eq!(breakdown.digest_bits, 512.0);
+        assert_eq!(breakdown.output_length, 8);
+        assert_eq!(breakdown.charset_size, 22);
+        assert!(breakdown.effective_bits() <= breakdown.output_bits);
+
+        let report = breakdown.lines().join("\n");
+        for component in ["Input fields", "Algorithm digest", "Output length", "Charset size", "Effective", "deterministic"] {
+            assert!(report.contains(component), "missing {}", component);
+        }
+    }
+
+    #[test]
+    fn test_policy_retry() {
+        // The default password 6AD8707! starts with a digit, so a leading-letter policy fails it
+        let config = AppConfig {
+            policy_regex: Some("^[A-F]".to_string()),
+            ..AppConfig::default()
+        };
+        let error = generate_password_hash(&config).unwrap_err().to_string();
+        assert_eq!(error, "Password does not match policy regex: ^[A-F]");
+        assert!(!error.contains("6AD8707"));
+
+        let config = AppConfig { policy_retry: 20, ..config };
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert!(Regex::new("^[A-F]").unwrap().is_match(&password));
+        assert_eq!(generate_password_hash(&config).unwrap(), generate_password_hash(&config).unwrap());
+
+        let impossible = AppConfig { policy_regex: Some("^z".to_string()), policy_retry: 3, ..AppConfig::default() };
+        assert!(generate_password_hash(&impossible).is_err());
+    }
+
+    #[test]
+    fn test_cut_percent() {
+        let mut config = AppConfig::default();
+        AppConfig::apply_hash_config(&mut config, "method:sha512,cut:50%,end:").unwrap();
+        assert_eq!(config.cut_percent, Some(50));
+        AppConfig::apply_hash_config(&mut config, "method:md5").unwrap();
+        config.validate().unwrap();
+        assert_eq!(result_get_password(&generate_password_hash(&config).unwrap(), &config).len(), 16);
+
+        // SHA512 at 50% is 64 chars, the upper bound
+        config.method = "SHA512".to_string();
+        assert_eq!(config.with_resolved_cut().cut_length, 64);
+        config.validate().unwrap();
+        assert_eq!(result_get_password(&generate_password_hash(&config).unwrap(), &config).len(), 64);
+        assert!(AppConfig { cut_percent: Some(60), ..config.clone() }.validate().is_err());
+
+        // A plain cut replaces the percentage
+        AppConfig::apply_hash_config(&mut config, "cut:10").unwrap();
+        assert_eq!((config.cut_percent, config.with_resolved_cut().cut_length), (None, 10));
+        assert!(AppConfig::apply_hash_config(&mut config, "cut:x%").is_err());
+    }
+
+    #[test]
+    fn test_diff_against_saved() {
+        let save_path = std::env::temp_dir().join(format!("pass-craft-diff-saved-{}.md", std::process::id()));
+        let save_file = save_path.to_str().unwrap();
+        let config = AppConfig { output_files: vec![save_file.to_string()], ..AppConfig::default() };
+        let result = generate_password_hash(&config).unwrap();
+        let password = result_get_password(&result, &config);
+
+        // No entry yet, then an unchanged regeneration: nothing to confirm
+        assert!(diff_against_saved(&config, &password, false).unwrap().is_empty());
+        save_result(&config, &result);
+        assert_eq!(diff_against_saved(&config, &password, false).unwrap(), [format!("{}: password unchanged", save_file)]);
+
+        // A changed password needs --yes
+        let changed = AppConfig { cut_length: 12, ..config.clone() };
+        let new_password = result_get_password(&generate_password_hash(&changed).unwrap(), &changed);
+        assert!(diff_against_saved(&changed, &new_password, false).is_err());
+        assert!(diff_against_saved(&changed, &new_password, true).unwrap()[0].contains("changed"));
+        fs::remove_file(save_file).unwrap();
+    }
+
+    #[test]
+    fn test_zbase32_encoding() {
+        // Reference vectors from the z-base-32 spec
+        assert_eq!(zbase32_encode(&[0xf0, 0xbf, 0xc7]), "6n9hq");
+        assert_eq!(zbase32_encode(&[0xd4, 0x7a, 0x04]), "4t7ye");
+        assert_eq!(zbase32_encode(&[0x00]), "yy");
+
+        let config = AppConfig {
+            output_encoding: "zbase32".to_string(),
+            cut_length: 20,
+            upper_start: 0,
+            end_char: String::new(),
+            ..AppConfig::default()
+        };
+        config.validate().unwrap();
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.chars().count(), 20);
+        assert!(password.bytes().all(|byte| ZBASE32_ALPHABET.contains(&byte)));
+        assert_eq!(generate_password_hash(&config).unwrap(), generate_password_hash(&config).unwrap());
+    }
+
+    #[test]
+    fn test_trace_provenance() {
+        let args = CliArgs::parse_from(["pass-craft", "--text", "name:john,email:john@gmail.com,site:john.com", "--hash", "cut:12"]);
+        let config = AppConfig::from_args(&args).unwrap();
+        let trace = config.trace_provenance();
+        let find = |field: &str| trace.iter().find(|(name, _, _)| *name == field).unwrap().clone();
+        assert_eq!(find("cut_length"), ("cut_length", "12".to_string(), ConfigSource::Cli));
+        assert_eq!(find("name"), ("name", "john".to_string(), ConfigSource::Cli));
+        assert_eq!(find("upper_start").2, ConfigSource::Default);
+        assert_eq!(find("end_char").2, ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_spaced_sslf() {
+        let compact = "name:john,email:john@gmail.com,site:john.com;method:sha256,cut:12,end:+";
+        assert_eq!(sslf_get_head("  name:john ; cut:12  "), "name:john");
+        assert_eq!(sslf_get_tail("  name:john ; cut:12  "), "cut:12");
+        for spaced in [
+            "name:john,email:john@gmail.com,site:john.com ; method:sha256,cut:12,end:+",
+            "  name: john , email: john@gmail.com , site: john.com\t;\tmethod: sha256 , cut: 12 , end: +  ",
+        ] {
+            let config = parse_sslf(spaced).unwrap();
+            assert_eq!(config, parse_sslf(compact).unwrap());
+            assert_eq!(build_base_text(&config).unwrap(), "john,john@gmail.com,john.com");
+        }
+    }
+
+    #[test]
+    fn test_argon2id() {
+        // Reference tags from OpenSSL's ARGON2ID KDF
+        assert_eq!(hex_encode(&argon2id(b"password", b"somesaltsalt", 64, 2, 2, 32).unwrap()),
+            "b4a99ba2e4de75b9c2bad4dea85a04dc3880109f1c2a7c45e34550f9bc1621c6");
+        assert_eq!(hex_encode(&argon2id(b"correct-horse-battery", b"0123456789abcdef", 600, 1, 3, 100).unwrap()),
+            "39b59411debdc8e11f38538b05d73955d5653cdddb9bda78fa22e58ba24b4b11ef6951313185a8eb97371164257326170f4c6abde2530112ff4e93f1ed708d3d3b481ed46d30bc7efbd8943eb67be955fec6e34a9e0483eb7b677ffc3e4a04f20bff72af");
+        
+        let mut config = AppConfig { name: "john".to_string(), site: "example.com".to_string(), ..Default::default() };
+        AppConfig::apply_hash_config(&mut config, "method:argon2id,cut:0,memory:64,iterations:1,parallelism:2").unwrap();
+        assert!(config.validate().is_err());
+        AppConfig::apply_hash_config(&mut config, "salt:somesaltsalt").unwrap();
+        config.validate().unwrap();
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.split('$').take(5).collect::<Vec<_>>(), ["", "argon2id", "v=19", "m=64,t=1,p=2", "c29tZXNhbHRzYWx0"]);
+        let tag = argon2id(build_base_text(&config).unwrap().as_bytes(), b"somesaltsalt", 64, 1, 2, 32).unwrap();
+        assert!(password.ends_with(base64_encode(&tag).trim_end_matches('=')));
+        
+        // Every digest path uses the configured costs
+        let costlier = AppConfig { argon2_memory: 128, ..config.clone() };
+        assert_eq!(raw_digest(&config).unwrap(), hex_encode(&tag));
+        assert_ne!(raw_digest(&costlier).unwrap(), raw_digest(&config).unwrap());
+        let mut builder = HashBuilder::with_config(&costlier).unwrap();
+        builder.update(build_base_text(&costlier).unwrap().as_bytes());
+        assert_eq!(hex_encode(&builder.digest().unwrap()), raw_digest(&costlier).unwrap());
+        
+        // A cut keeps the usual password transforms
+        let cut = AppConfig { cut_length: 8, ..config.clone() };
+        assert_eq!(result_get_password(&generate_password_hash(&cut).unwrap(), &cut).len(), 8);
+        
+        assert!(AppConfig { argon2_memory: 8, ..config.clone() }.validate().is_err());
+        assert!(AppConfig { argon2_iterations: 0, ..config.clone() }.validate().is_err());
+        assert!(AppConfig { argon2_parallelism: 1000, ..config.clone() }.validate().is_err());
+        assert!(AppConfig { method: "SHA256".to_string(), ..config }.validate().is_err());
+    }
+
+    #[test]
+    fn test_retry_with_backoff() {
+        let password = "B5CB304+";
+        let suffix = get_string_hash(password, "SHA1").unwrap().to_uppercase()[5..].to_string();
+        
+        // Mocked range endpoint fails twice before answering
+        let calls = std::cell::Cell::new(0);
+        let flaky = |_: &str| -> Result<String> {
+            calls.set(calls.get() + 1);
+            if calls.get() <= 2 {
+                return Err(anyhow::anyhow!("503 Service Unavailable"));
+            }
+            Ok(format!("{}:7\r\n", suffix))
+        };
+        let retrying = |prefix: &str| retry_with_backoff(2, Duration::from_millis(1), || flaky(prefix));
+        assert_eq!(hibp_check(password, retrying).unwrap(), 7);
+        assert_eq!(calls.get(), 3);
+        
+        // One retry isn't enough, the last error is reported
+        calls.set(0);
+        let error = retry_with_backoff(1, Duration::from_millis(1), || flaky("B5CB3")).unwrap_err();
+        assert_eq!(error.to_string(), "503 Service Unavailable (after 2 attempts)");
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_salt() {
+        let config = AppConfig { name: "john".to_string(), site: "example.com".to_string(), ..Default::default() };
+        let password = |config: &AppConfig| result_get_password(&generate_password_hash(config).unwrap(), config);
+        
+        let mut alice = config.clone();
+        AppConfig::apply_hash_config(&mut alice, "salt:alice").unwrap();
+        let mut bob = config.clone();
+        AppConfig::apply_hash_config(&mut bob, "salt:bob").unwrap();
+        assert_eq!(build_base_text(&alice).unwrap(), "john,,example.com,alice");
+        assert_ne!(password(&alice), password(&bob));
+        assert_ne!(password(&alice), password(&config));
+        
+        // @file: reads the salt from a file
+        let salt_path = std::env::temp_dir().join(format!("pass-craft-salt-{}.txt", std::process::id()));
+        let salt_path = salt_path.to_str().unwrap();
+        fs::write(salt_path, "alice\n").unwrap();
+        let mut from_file = config.clone();
+        AppConfig::apply_hash_config(&mut from_file, &format!("salt:@file:{}", salt_path)).unwrap();
+        assert_eq!(password(&from_file), password(&alice));
+        assert_eq!(AppConfig::from_toml(&from_file.to_toml()).unwrap().salt.as_deref(), Some("alice"));
+        
+        fs::remove_file(salt_path).unwrap();
+        assert!(AppConfig::apply_hash_config(&mut from_file, &format!("salt:@file:{}", salt_path)).is_err());
+    }
+
+    #[test]
+    fn test_cut_bits() {
+        let mut config = AppConfig { name: "john".to_string(), site: "example.com".to_string(), end_char: "".to_string(), ..Default::default() };
+        AppConfig::apply_hash_config(&mut config, "method:sha256,cut:64bits").unwrap();
+        assert_eq!(config.cut_bits, Some(64));
+        config.validate().unwrap();
+        assert_eq!(result_get_password(&generate_password_hash(&config).unwrap(), &config).len(), 16);
+        
+        // Six bits per base64 character need fewer of them
+        let base64 = AppConfig { output_encoding: "base64".to_string(), ..config.clone() };
+        base64.validate().unwrap();
+        assert_eq!(result_get_password(&generate_password_hash(&base64).unwrap(), &base64).len(), 11);
+        
+        // MD5 can't supply 160 bits
+        assert!(AppConfig { method: "MD5".to_string(), cut_bits: Some(160), ..config.clone() }.validate().is_err());
+        assert!(AppConfig { cut_bits: Some(0), ..config.clone() }.validate().is_err());
+        assert!(AppConfig::apply_hash_config(&mut config, "cut:xbits").is_err());
+        
+        AppConfig::apply_hash_config(&mut config, "cut:10").unwrap();
+        assert_eq!((config.cut_bits, config.with_resolved_cut().cut_length), (None, 10));
+    }
+
+    #[test]
+    fn test_input_hash() {
+        let config = AppConfig { name: "john".to_string(), site: "example.com".to_string(), ..Default::default() };
+        let digest = raw_digest(&config).unwrap();
+        let generated = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(format_input_hash(&digest, &config).unwrap(), generated);
+        assert_eq!(format_input_hash(&digest.to_uppercase(), &config).unwrap(), generated);
+        
+        assert_eq!(hex_decode("00ff10"), Some(vec![0, 255, 16]));
+        assert!(format_input_hash("abc", &config).is_err());
+        assert!(format_input_hash("zz", &config).is_err());
+        assert!(format_input_hash("", &config).is_err());
+    }
+
+    #[test]
+    fn test_multibyte_transforms() {
+        // A multi-byte end character lands inside the uppercased prefix
+        let config = AppConfig {
+            name: "john".to_string(),
+            site: "example.com".to_string(),
+            end_char: "€".to_string(),
+            upper_start: 8,
+            ..Default::default()
+        };
+        let password = result_get_password(&generate_password_hash(&config).unwrap(), &config);
+        assert_eq!(password.chars().count(), 8);
+        assert!(password.ends_with('€'));
+        assert_eq!(password, password.to_uppercase());
+        
+        // Truncation after the end character counts characters, not bytes
+        let reordered = AppConfig { pipeline: vec!["endchar".to_string(), "case".to_string(), "truncate".to_string()], cut_length: 7, ..config.clone() };
+        let password = result_get_password(&generate_password_hash(&reordered).unwrap(), &reordered);
+        assert_eq!(password.chars().count(), 7);
+        let case_only = AppConfig { upper_start: 5, ..config };
+        let password = result_get_password(&generate_password_hash(&case_only).unwrap(), &case_only);
+        assert_eq!(password.chars().count(), 8);
+    }
+
+    #[test]
+    fn test_list_algorithms() {
+        let listing = list_algorithms();
+        assert_eq!(listing, list_algorithms());
+        
+        let names: Vec<&str> = listing.lines().map(|line| line.split_whitespace().next().unwrap()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        assert_eq!(names.len(), ALGORITHMS.len());
+        assert!(listing.contains("MD5        16 bytes weak"));
+        assert!(registry_json().starts_with("[{\"name\": \"ARGON2ID\""));
+        
+        // The --help text names every registered algorithm
+        let help = <CliArgs as clap::CommandFactory>::command().get_long_about().unwrap().to_string().to_uppercase();
+        for algorithm in &ALGORITHMS {
+            assert!(help.contains(algorithm.name), "--help misses {}", algorithm.name);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "verify-only"))]
+mod verify_only_tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_only_disables_generation() {
+        let config = AppConfig { name: "john".to_string(), site: "example.com".to_string(), ..Default::default() };
+        let error = generate_password_hash(&config).unwrap_err();
+        assert_eq!(error.to_string(), GENERATION_DISABLED);
+        assert!(verify_check_digit(&format!("abc{}", check_digit("abc"))));
+    }
+}
@@ -1,722 +1,330 @@
-//! Password Hash Generator
-//!
-//! A cross-platform password hash generation tool supporting multiple hash algorithms and custom formatting.
-//! Supports Windows, Linux, macOS on x86_64 and AArch64 architectures.
-//!
-//! # Features
-//! - Multi-platform support (Windows, Linux, macOS)
-//! - Multiple hash algorithms (MD5, SHA1, SHA256, SHA512)
-//! - Flexible configuration system (CLI args, environment variables, config files)
-//! - Custom hash output formatting (truncation, end characters, case conversion)
-//! - Result saving and file operations
+//! Password Hash Generator command line entry point
 
-use std::collections::HashMap;
-use std::fs;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
-use clap::{Arg, Command, Parser};
-use regex::Regex;
-use sha1::Sha1;
-use sha2::{Sha256, Sha512, Digest};
-use anyhow::Result;
-use log::{info, error, warn, debug};
-use serde::Deserialize;
-use chrono::{Utc, DateTime};
+use clap::Parser;
+#[cfg(not(feature = "verify-only"))]
+use std::time::Instant;
+use log::info;
+use pass_craft::*;
 
-/// Platform information
-#[derive(Debug)]
-struct PlatformInfo {
-    os: String,
-    arch: String,
-    family: String,
-}
-
-impl PlatformInfo {
-    fn new() -> Self {
-        Self {
-            os: std::env::consts::OS.to_string(),
-            arch: std::env::consts::ARCH.to_string(),
-            family: std::env::consts::FAMILY.to_string(),
-        }
-    }
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
     
-    fn display(&self) -> String {
-        format!("{}-{}", self.os, self.arch)
-    }
-}
-
-/// Command line arguments
-#[derive(Parser, Debug)]
-#[command(
-    author,
-    version,
-    about = "Cross-platform Password Hash Generator",
-    long_about = r#"Password Hash Generator
-
-A cross-platform password hash generation tool supporting multiple hash algorithms
-and custom formatting. Supports Windows, Linux, macOS on x86_64 and AArch64 architectures.
-
-Features:
-• Multi-platform support (Windows, Linux, macOS)
-• Multiple hash algorithms (MD5, SHA1, SHA256, SHA512)  
-• Flexible configuration system (CLI args, environment variables, config files)
-• Custom hash output formatting (truncation, end characters, case conversion)
-• Result saving and file operations
-
-Examples:
-  # Basic usage
-  pass-craft --text "name:john,site:example.com" --hash "method:sha256,cut:10"
-
-  # Read configuration from file
-  pass-craft --file config.txt --save passwords.txt
-
-  # Show platform information  
-  pass-craft --show-platform
-
-  # Show configuration
-  pass-craft --show-config --text "name:test,site:example.com"
-"#,
-    after_help = "See https://github.com/ymc-github/pass-craft for more information."
-)]
-struct CliArgs {
-    /// Command
-    #[arg(default_value = "add")]
-    cmd: String,
-
-    /// Text parameter
-    #[arg(long)]
-    text: Option<String>,
-
-    /// Hash parameters
-    #[arg(long)]
-    hash: Option<String>,
-
-    /// String key-value configuration
-    #[arg(long)]
-    slkv: Option<String>,
-
-    /// Secure string format configuration
-    #[arg(long)]
-    sslf: Option<String>,
-
-    /// Save file path
-    #[arg(long)]
-    save: Option<String>,
-
-    /// Input file path
-    #[arg(long)]
-    file: Option<String>,
-
-    /// Show configuration and exit
-    #[arg(long, default_value = "false")]
-    show_config: bool,
-
-    /// Show platform information and exit
-    #[arg(long, default_value = "false")]
-    show_platform: bool,
-}
-
-/// Application configuration
-#[derive(Debug, Deserialize, Clone)]
-struct AppConfig {
-    // Hash algorithm configuration
-    method: String,
-    cut_length: usize,
-    end_char: String,
-    upper_start: usize,
-    
-    // User information
-    name: String,
-    email: String,
-    site: String,
-    
-    // File configuration
-    input_file: Option<String>,
-    output_file: Option<String>,
-    
-    // Platform specific configuration
-    #[serde(default)]
-    platform_identifier: String,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            method: "SHA512".to_string(),
-            cut_length: 8,
-            end_char: "!".to_string(),
-            upper_start: 3,
-            name: "".to_string(),
-            email: "".to_string(),
-            site: "".to_string(),
-            input_file: None,
-            output_file: None,
-            platform_identifier: "".to_string(),
-        }
-    }
-}
-
-impl AppConfig {
-    fn new() -> Result<Self> {
-        let cli_args = CliArgs::parse();
-        
-        // Set default configuration
-        let mut config = AppConfig::default();
-        
-        // Get configuration from various parameters
-        if let Some(text) = &cli_args.text {
-            Self::apply_text_config(&mut config, text);
-        }
-        
-        if let Some(hash) = &cli_args.hash {
-            Self::apply_hash_config(&mut config, hash);
-        }
-        
-        if let Some(slkv) = &cli_args.slkv {
-            Self::apply_slkv_config(&mut config, slkv);
-        }
-        
-        if let Some(sslf) = &cli_args.sslf {
-            Self::apply_sslf_config(&mut config, sslf);
-        }
-        
-        // Get configuration from file
-        if let Some(file_path) = &cli_args.file {
-            if let Ok(file_config) = Self::load_from_file(file_path) {
-                config = file_config;
-            }
-        }
-        
-        config.input_file = cli_args.file.clone();
-        config.output_file = cli_args.save.clone();
-        
-        Ok(config)
+    let platform = PlatformInfo::new();
+    
+    // First parse command line arguments
+    let cli_args = CliArgs::parse();
+    
+    // Keep stdout clean for eval
+    if cli_args.as_env.is_some() {
+        set_quiet(true);
     }
     
-    fn apply_text_config(config: &mut AppConfig, text: &str) {
-        // Parse text configuration format: name:value,email:value,site:value
-        let pairs: Vec<&str> = text.split(',').collect();
-        for pair in pairs {
-            if let Some((key, value)) = pair.split_once(':') {
-                match key.trim() {
-                    "name" => config.name = value.trim().to_string(),
-                    "email" => config.email = value.trim().to_string(),
-                    "site" => config.site = value.trim().to_string(),
-                    _ => {}
-                }
-            }
-        }
+    // Check help and version parameters
+    // let args: Vec<String> = std::env::args().collect();
+    // if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+    //     print_help();
+    //     return Ok(());
+    // }
+    
+    // if args.iter().any(|arg| arg == "--version" || arg == "-v") {
+    //     println!("pass-craft v{}", env!("CARGO_PKG_VERSION"));
+    //     return Ok(());
+    // }
+    
+    // Show platform information
+    if cli_args.show_platform {
+        info_step("Platform Information", 50, '=');
+        println!("Operating System: {}", platform.os);
+        println!("Architecture: {}", platform.arch);
+        println!("Family: {}", platform.family);
+        println!("Display Format: {}", platform.display());
+        return Ok(());
     }
     
-    fn apply_hash_config(config: &mut AppConfig, hash: &str) {
-        // Parse hash configuration format: method:value,cut:value,end:value,upper-start:value
-        let pairs: Vec<&str> = hash.split(',').collect();
-        for pair in pairs {
-            if let Some((key, value)) = pair.split_once(':') {
-                match key.trim() {
-                    "method" => config.method = value.trim().to_string(),
-                    "cut" => config.cut_length = value.trim().parse().unwrap_or(8),
-                    "end" => config.end_char = value.trim().to_string(),
-                    "upper-start" => config.upper_start = value.trim().parse().unwrap_or(3),
-                    _ => {}
-                }
-            }
+    // Serve passwords to a long-running client such as a shell plugin
+    #[cfg(not(feature = "verify-only"))]
+    if let Some(socket) = &cli_args.server {
+        let token = cli_args.server_token.clone()
+            .or_else(|| std::env::var("PASS_CRAFT_SERVER_TOKEN").ok())
+            .unwrap_or_default();
+        info_status(&format!("{} - Serving on {}", get_time_now(), socket), 0);
+        set_quiet(true);
+        if let Err(e) = start_server(socket, &token) {
+            info_status(&format!("{} - Server failed: {}", get_time_now(), e), 1);
+            std::process::exit(1);
         }
+        return Ok(());
     }
     
-    fn apply_slkv_config(config: &mut AppConfig, slkv: &str) {
-        Self::apply_text_config(config, slkv);
-        Self::apply_hash_config(config, slkv);
+    // Print the algorithm registry for tooling
+    if cli_args.dump_registry {
+        println!("{}", registry_json());
+        return Ok(());
     }
     
-    fn apply_sslf_config(config: &mut AppConfig, sslf: &str) {
-        let head = sslf_get_head(sslf);
-        let tail = sslf_get_tail(sslf);
-        
-        Self::apply_text_config(config, &head);
-        Self::apply_hash_config(config, &tail);
+    // List the supported algorithms
+    if cli_args.list_algorithms {
+        println!("{}", list_algorithms());
+        return Ok(());
     }
     
-    fn load_from_file(file_path: &str) -> Result<Self> {
-        // let content = fs::read_to_string(file_path)?;
-        // let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        
-        // 过滤空行和只包含空白字符的行
-        // let lines: Vec<String> = content
-        //     .lines()
-        //     .map(|s| s.trim())  // 去除前后空白
-        //     .filter(|line| !line.is_empty())  // 过滤空行
-        //     .map(|s| s.to_string())
-        //     .collect();
-
-        // 使用现有的sslf_load_file函数，它会清理注释和空行
-        let lines = sslf_load_file(file_path, "");
-
-        if let Some(last_line) = lines.last() {
-            let mut config = AppConfig::default();
-            Self::apply_sslf_config(&mut config, last_line);
-            Ok(config)
-        } else {
-            debug!("配置文件 '{}' 为空或没有有效内容，使用默认配置", file_path);
-            Ok(AppConfig::default())
+    // Exercise the core paths as a deployment smoke test
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.health_check {
+        info_step("Health Check", 50, '=');
+        match health_check() {
+            Ok(()) => {
+                info_status(&format!("{} - Health check passed", get_time_now()), 0);
+                return Ok(());
+            }
+            Err(e) => {
+                info_status(&format!("{} - Health check failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
         }
     }
     
-    /// Display configuration information (for --show-config)
-    fn display_config(&self) {
-        info_step("Password Hash Generator Configuration", 60, '=');
-        
-        // User information configuration
-        println!("👤 User Information:");
-        println!("  Name: {}", self.name);
-        println!("  Email: {}", self.email);
-        println!("  Site: {}", self.site);
-        
-        // Hash algorithm configuration
-        println!("🔑 Hash Algorithm Configuration:");
-        println!("  Method: {}", self.method);
-        println!("  Cut Length: {}", self.cut_length);
-        println!("  End Character: {}", self.end_char);
-        println!("  Upper Start: {}", self.upper_start);
-        
-        // File configuration
-        println!("📁 File Configuration:");
-        println!("  Input File: {}", self.input_file.as_deref().unwrap_or("Not set"));
-        println!("  Output File: {}", self.output_file.as_deref().unwrap_or("Not set"));
-        
-        // Platform configuration
-        println!("🔧 Platform Configuration:");
-        println!("  Platform Identifier: {}", self.platform_identifier);
+    // Write a starter config file
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.init_config {
+        let Some(dir) = config_dir() else {
+            info_status(&format!("{} - Could not resolve the config directory", get_time_now()), 1);
+            std::process::exit(1);
+        };
         
-        // Configuration validation status
-        println!("✅ Configuration Validation:");
-        match self.validate() {
-            Ok(()) => info_status("Status: Valid", 0),
-            Err(e) => info_status(&format!("Status: Invalid - {}", e), 1),
+        match init_config(&dir) {
+            Ok(path) => info_status(&format!("{} - Created config file: {}", get_time_now(), path.display()), 0),
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
         }
+        return Ok(());
     }
     
-    /// Validate configuration
-    fn validate(&self) -> Result<()> {
-        let valid_methods = ["MD5", "SHA1", "SHA256", "SHA512"];
-        if !valid_methods.contains(&self.method.to_uppercase().as_str()) {
-            return Err(anyhow::anyhow!("Unsupported hash algorithm: {}", self.method));
-        }
-        
-        if self.cut_length == 0 || self.cut_length > 64 {
-            return Err(anyhow::anyhow!("Cut length must be between 1-64"));
-        }
-        
-        if self.upper_start > self.cut_length {
-            return Err(anyhow::anyhow!("Upper start position cannot exceed cut length"));
-        }
+    info!("🚀 Starting Password Hash Generator on {}", platform.display());
+    
+    // Convert an old head;tail config file to TOML
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.cmd == "migrate" {
+        let (Some(from), Some(to)) = (&cli_args.from, &cli_args.to) else {
+            info_status(&format!("{} - Migrate requires --from and --to", get_time_now()), 1);
+            std::process::exit(1);
+        };
         
-        Ok(())
-    }
-}
-
-// Calculate string hash value
-fn get_string_hash(string: &str, hash_name: &str) -> String {
-    let hash_name = hash_name.to_uppercase();
-    
-    match hash_name.as_str() {
-        "MD5" => {
-            let digest = md5::compute(string.as_bytes());
-            format!("{:x}", digest)
-        }
-        "SHA1" => {
-            let mut hasher = Sha1::new();
-            hasher.update(string.as_bytes());
-            format!("{:x}", hasher.finalize())
-        }
-        "SHA256" => {
-            let mut hasher = Sha256::new();
-            hasher.update(string.as_bytes());
-            format!("{:x}", hasher.finalize())
-        }
-        "SHA512" => {
-            let mut hasher = Sha512::new();
-            hasher.update(string.as_bytes());
-            format!("{:x}", hasher.finalize())
+        match migrate_to_toml(from, to) {
+            Ok(_) => info_status(&format!("{} - Migrated {} to {}", get_time_now(), from, to), 0),
+            Err(e) => {
+                info_status(&format!("{} - Migration failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
         }
-        _ => panic!("Unsupported hash algorithm: {}", hash_name),
-    }
-}
-
-// Check if any element in value is in check_list
-fn oneof(value: &str, check_list: &[&str]) -> bool {
-    if value.is_empty() {
-        return false;
+        return Ok(());
     }
     
-    let items: Vec<&str> = value.split(',').map(|item| item.trim()).collect();
-    
-    for item in items {
-        if check_list.contains(&item) {
-            return true;
+    // Validate a typed password's check character
+    if let Some(password) = &cli_args.verify_check_digit {
+        if verify_check_digit(password) {
+            info_status(&format!("{} - Check character is valid", get_time_now()), 0);
+            return Ok(());
         }
-    }
-    false
-}
-
-// Check if all elements in value are in check_list
-fn everyof(value: &str, check_list: &[&str]) -> bool {
-    if value.is_empty() {
-        return false;
+        info_status(&format!("{} - Check character does not match, the password was mistyped", get_time_now()), 1);
+        std::process::exit(1);
     }
     
-    let items: Vec<&str> = value.split(',').map(|item| item.trim()).collect();
-    
-    for item in items {
-        if !check_list.contains(&item) {
-            return false;
-        }
-    }
-    true
-}
-
-// Check if string is empty or contains only whitespace
-fn string_is_empty(value: &str) -> bool {
-    value.trim().is_empty()
-}
-
-// Return default value if string is empty
-fn string_get(value: &str, default_value: &str) -> String {
-    if string_is_empty(value) {
-        default_value.to_string()
-    } else {
-        value.to_string()
-    }
-}
-
-// Remove empty lines from multi-line text
-fn mlt_del_emptyline(lines: &[String]) -> Vec<String> {
-    lines.iter()
-        .filter(|line| !line.trim().is_empty())
-        .cloned()
-        .collect()
-}
-
-// Load multi-line text from file, return default text if file doesn't exist
-fn mlt_load_file(loc: &str, default_text: &str) -> Vec<String> {
-    if Path::new(loc).exists() {
-        if let Ok(file) = fs::File::open(loc) {
-            let reader = io::BufReader::new(file);
-            reader.lines().filter_map(Result::ok).collect()
-        } else {
-            vec![]
-        }
-    } else {
-        if default_text.is_empty() {
-            vec![]
-        } else {
-            default_text.lines().map(|s| s.to_string()).collect()
+    // Print the digest of a file
+    #[cfg(not(feature = "verify-only"))]
+    if let Some(file_path) = &cli_args.hash_file {
+        let method = cli_args.method.as_deref().unwrap_or("SHA512");
+        // Argon2id costs and salt come from --hash
+        let config = match AppConfig::new() {
+            Ok(config) => config,
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        };
+        match hash_file(file_path, method, &config, cli_args.strict) {
+            Ok((digest, warnings)) => {
+                for warning in warnings {
+                    info_status(&format!("{} - {}", get_time_now(), warning), 2);
+                }
+                println!("{}", digest);
+            }
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
         }
-    }
-}
-
-// Get last line of multi-line text
-fn mlt_get_lastline(lines: &[String]) -> String {
-    lines.last().cloned().unwrap_or_default()
-}
-
-// Get value for specified key from key-value string
-fn slkv_get(value: &str, slkv: &str, case_sensitive: bool) -> String {
-    if slkv.is_empty() {
-        return String::new();
+        return Ok(());
     }
     
-    let pairs: Vec<&str> = slkv.split(',').map(|pair| pair.trim()).collect();
-    let search_key = if case_sensitive {
-        value.trim().to_string()
-    } else {
-        value.trim().to_uppercase()
-    };
-    
-    for pair in pairs {
-        if pair.contains(':') {
-            let parts: Vec<&str> = pair.splitn(2, ':').collect();
-            let key = parts[0].trim();
-            let val = parts[1].trim();
-            
-            let compare_key = if case_sensitive {
-                key.to_string()
-            } else {
-                key.to_uppercase()
-            };
-            
-            if compare_key == search_key {
-                return val.to_string();
+    // Explain which settings differ between two head;tail configs
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.explain_diff {
+        let (Some(a), Some(b)) = (&cli_args.a, &cli_args.b) else {
+            info_status(&format!("{} - --explain-diff requires --a and --b", get_time_now()), 1);
+            std::process::exit(1);
+        };
+        
+        let parse = |sslf: &str| {
+            let mut config = AppConfig::default();
+            AppConfig::apply_sslf_config(&mut config, sslf).map(|_| config)
+        };
+        match parse(a).and_then(|a| parse(b).map(|b| explain_diff(&a, &b))) {
+            Ok(diffs) if diffs.is_empty() => info_status(&format!("{} - Configurations are identical", get_time_now()), 0),
+            Ok(diffs) => {
+                info_step("Configuration Differences", 50, '=');
+                for diff in diffs {
+                    let kind = if diff.hash_input { "hash input" } else { "formatting" };
+                    println!("{:<18} {} -> {} ({})", diff.field, diff.a, diff.b, kind);
+                }
+            }
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
             }
         }
+        return Ok(());
     }
     
-    String::new()
-}
-
-// Get part before semicolon
-fn sslf_get_head(data: &str) -> String {
-    data.split(';').next().unwrap_or(data).to_string()
-}
-
-// Get part after semicolon
-fn sslf_get_tail(data: &str) -> String {
-    data.split(';').nth(1).unwrap_or("").to_string()
-}
-
-// Load file and clean comments and empty lines
-fn sslf_load_file(loc: &str, default_text: &str) -> Vec<String> {
-    let lines = mlt_load_file(loc, default_text);
-    
-    // Compile regex patterns
-    let comment_re = Regex::new(r"^#.*").unwrap();
-    let html_comment_re = Regex::new(r"<!--.*-->").unwrap();
-    
-    lines.iter()
-        .filter_map(|line| {
-            // Remove comment lines and HTML comments
-            let clean_line = comment_re.replace(line, "");
-            let clean_line = html_comment_re.replace(&clean_line, "");
-            
-            if clean_line.trim().is_empty() {
-                None
-            } else {
-                Some(clean_line.to_string())
+    // Report saved timestamps from a wrong clock
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.cmd == "check-clock" {
+        let Some(path) = cli_args.target.as_ref().or(cli_args.file.as_ref()) else {
+            info_status(&format!("{} - check-clock requires a save file path", get_time_now()), 1);
+            std::process::exit(1);
+        };
+        
+        match check_clock(path, &get_time_now()) {
+            Ok(issues) if issues.is_empty() => info_status(&format!("{} - All timestamps in {} are in order", get_time_now(), path), 0),
+            Ok(issues) => {
+                for issue in &issues {
+                    info_status(&format!("{} - {}", get_time_now(), issue), 2);
+                }
+                std::process::exit(1);
             }
-        })
-        .collect()
-}
-
-// Remove name:, email:, site: prefixes from data
-fn shtkv_get_pure_v(data: &str) -> String {
-    if data.is_empty() {
-        return String::new();
+            Err(e) => {
+                info_status(&format!("{} - Clock check failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
     
-    // Compile regex patterns
-    let prefix_re = Regex::new(r"(name:|email:|site:)").unwrap();
-    let trailing_comma_re = Regex::new(r",$").unwrap();
-    
-    let cleaned = prefix_re.replace_all(data, "");
-    let cleaned = trailing_comma_re.replace(&cleaned, "");
-    cleaned.trim().to_string()
-}
-
-// Get filename from path
-fn path_get_name(path: &str) -> String {
-    Path::new(path)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("")
-        .to_string()
-}
-
-// Get directory part from path
-fn path_get_dirs(path: &str) -> String {
-    Path::new(path)
-        .parent()
-        .and_then(|parent| parent.to_str())
-        .unwrap_or("")
-        .to_string()
-}
-
-// Normalize path separators
-fn path_normalize(path: &str, search: &str, replace: &str) -> String {
-    path.replace(search, replace)
-}
-
-// Check if path exists
-fn os_path_exist(loc: &str) -> bool {
-    Path::new(loc).exists()
-}
-
-// Create directory
-fn os_path_make(loc: &str) -> Result<(), std::io::Error> {
-    if !loc.is_empty() && !os_path_exist(loc) {
-        fs::create_dir_all(loc)
-    } else {
-        Ok(())
-    }
-}
-
-// Add password to file
-fn add_password_to_file(loc: &str, password: &str) -> Result<(), std::io::Error> {
-    if os_path_exist(loc) {
-        let content = fs::read_to_string(loc)?;
-        let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        lines.push(password.to_string());
+    // Drop old entries from a save file's archive
+    #[cfg(not(feature = "verify-only"))]
+    if cli_args.cmd == "prune-archived" {
+        let (Some(path), Some(days)) = (cli_args.target.as_ref().or(cli_args.file.as_ref()), cli_args.older_than) else {
+            info_status(&format!("{} - prune-archived requires a save file path and --older-than <days>", get_time_now()), 1);
+            std::process::exit(1);
+        };
         
-        fs::write(loc, lines.join("\n"))?;
-    } else {
-        fs::write(loc, password)?;
+        match prune_archived(path, days, &get_time_now()) {
+            Ok(pruned) => info_status(&format!("{} - Pruned {} archived entries older than {} days", get_time_now(), pruned, days), 0),
+            Err(e) => {
+                info_status(&format!("{} - Prune failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
     
-    Ok(())
-}
-
-// Wrap text with HTML comments
-fn html_comment_wrap(text: &str) -> String {
-    format!("<!-- {} -->", text)
-}
-
-// Remove HTML comment wrapping
-fn html_comment_unwrap(text: &str) -> String {
-    text.trim()
-        .trim_start_matches("<!--")
-        .trim_end_matches("-->")
-        .trim()
-        .to_string()
-}
-
-/// Get current time in formatted string
-fn get_time_now() -> String {
-    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
-}
-
-/// Display a step header with centered text
-fn info_step(msg: &str, length: usize, fillchar: char) {
-    let msg_len = msg.chars().count();
-    if msg_len >= length {
-        println!("{}", msg);
-        return;
+    // Flag saved entries made by another pass-craft version
+    if cli_args.cmd == "verify-file" {
+        let Some(path) = cli_args.target.as_ref().or(cli_args.file.as_ref()) else {
+            info_status(&format!("{} - verify-file requires a save file path", get_time_now()), 1);
+            std::process::exit(1);
+        };
+        
+        match verify_file(path, env!("CARGO_PKG_VERSION")) {
+            Ok(warnings) if warnings.is_empty() => info_status(&format!("{} - All versioned entries in {} match this version", get_time_now(), path), 0),
+            Ok(warnings) => {
+                for warning in &warnings {
+                    info_status(&format!("{} - {}", get_time_now(), warning), 2);
+                }
+            }
+            Err(e) => {
+                info_status(&format!("{} - Verification failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
     
-    let padding_len = (length - msg_len) / 2;
-    let padding = fillchar.to_string().repeat(padding_len);
-    
-    // Use format! for precise length control
-    let formatted = format!("{}{}{}", padding, msg, padding);
-    // Truncate to exact length (there might be 1 character difference for odd lengths)
-    println!("{}", &formatted[..length.min(formatted.len())]);
+    // Verify-only builds stop after the verification commands
+    generate_and_save(&cli_args, &platform)
 }
 
-/// Display status message with appropriate icon
-fn info_status(msg_body: &str, status: u8) {
-    let icon = match status {
-        0 => "✅", // Success
-        1 => "❌", // Error
-        2 => "⚠️",  // Warning
-        _ => "ℹ️", // Info
-    };
-    println!("{} {}", icon, msg_body);
+#[cfg(feature = "verify-only")]
+fn generate_and_save(_cli_args: &CliArgs, _platform: &PlatformInfo) -> Result<(), Box<dyn std::error::Error>> {
+    info_status(&format!("{} - {}", get_time_now(), GENERATION_DISABLED), 1);
+    std::process::exit(1);
 }
 
-/// Generate password hash
-fn generate_password_hash(config: &AppConfig) -> Result<String> {
-    info_step("Generating Password Hash", 50, '-');
-    
-    // Generate base text
-    let base_text = format!("{},{},{}", config.name, config.email, config.site);
-    info_status(&format!("{} - Base text: {}", get_time_now(), base_text), 3);
-    
-    // Calculate hash value
-    let hash_value = get_string_hash(&base_text, &config.method);
-    info_status(&format!("{} - Raw {} hash: {}", get_time_now(), config.method, hash_value), 3);
-    
-    // Handle hash truncation
-    let mut hash_cut = hash_value[..hash_value.len().min(config.cut_length)].to_string();
-    info_status(&format!("{} - Truncated to {} chars: {}", get_time_now(), config.cut_length, hash_cut), 3);
-    
-    // Handle end character
-    if !config.end_char.is_empty() {
-        if let Some(end_char) = config.end_char.chars().next() {
-            if !hash_cut.is_empty() {
-                hash_cut.pop();
-                hash_cut.push(end_char);
-                info_status(&format!("{} - Added end character '{}'", get_time_now(), end_char), 3);
+// Batch, variant and single-password generation, with saving
+#[cfg(not(feature = "verify-only"))]
+fn generate_and_save(cli_args: &CliArgs, platform: &PlatformInfo) -> Result<(), Box<dyn std::error::Error>> {
+    // Process every line of the input file
+    if cli_args.cmd == "batch" {
+        let Some(file_path) = &cli_args.file else {
+            info_status(&format!("{} - Batch mode requires --file", get_time_now()), 1);
+            std::process::exit(1);
+        };
+        
+        // Skip input files unchanged since the last run
+        let mut next_state = None;
+        let files = batch_input_files(file_path).and_then(|files| match &cli_args.since_last_run {
+            Some(state_path) => {
+                let (modified, state) = modified_since_last_run(&files, &load_run_state(state_path))?;
+                info_status(&format!("{} - {} of {} input files changed since the last run", get_time_now(), modified.len(), files.len()), 3);
+                next_state = Some((state_path, state));
+                Ok(modified)
+            }
+            None => Ok(files),
+        });
+        
+        let comment_prefixes = cli_args.comment_prefixes.as_deref().map(parse_comment_prefixes).unwrap_or_default();
+        let configs = cli_args.line_range.as_deref()
+            .map(parse_line_range)
+            .transpose()
+            .and_then(|line_range| {
+                let mut configs = Vec::new();
+                for file in files? {
+                    if cli_args.strict_utf8 {
+                        read_text_file(&file, true)?;
+                    }
+                    configs.extend(batch_load_configs(&file, line_range, &comment_prefixes)?);
+                }
+                Ok(configs)
+            });
+        let options = BatchOptions {
+            save_paths: cli_args.save.clone(),
+            rate_limit: cli_args.rate_limit,
+            flush_every: cli_args.flush_every,
+        };
+        match configs.and_then(|configs| run_batch(&configs, &options)) {
+            Ok(results) => {
+                info_step("Batch Generation Complete", 50, '=');
+                info_status(&format!("{} - Generated {} passwords", get_time_now(), results.len()), 0);
+                if let Some((state_path, state)) = &next_state {
+                    if let Err(e) = save_run_state(state_path, state) {
+                        info_status(&format!("{} - Could not record run state in {}: {}", get_time_now(), state_path, e), 2);
+                    }
+                }
+            }
+            Err(e) => {
+                info_step("Batch Generation Failed", 50, '!');
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
             }
         }
-    }
-    
-    // Handle case conversion
-    if config.upper_start <= hash_cut.len() {
-        let upper_part = hash_cut[..config.upper_start].to_uppercase();
-        let lower_part = &hash_cut[config.upper_start..];
-        hash_cut = format!("{}{}", upper_part, lower_part);
-        info_status(&format!("{} - First {} characters uppercased", get_time_now(), config.upper_start), 3);
-    }
-    
-    // Generate final result
-    let result = format!("{},{},{}", config.name, hash_cut, config.site);
-    info_status(&format!("{} - Final result: {}", get_time_now(), result), 0);
-    
-    Ok(result)
-}
-
-/// Display help information
-// fn print_help() {
-//     println!("Password Hash Generator v{}", env!("CARGO_PKG_VERSION"));
-//     println!();
-//     println!("A cross-platform password hash generation tool");
-//     println!();
-//     println!("USAGE:");
-//     println!("    pass-craft [COMMAND] [OPTIONS]");
-//     println!();
-//     println!("COMMANDS:");
-//     println!("    add             Add new password (default command)");
-//     println!("    generate        Generate password hash");
-//     println!();
-//     println!("OPTIONS:");
-//     println!("    --text <TEXT>           Text parameter (name:value,email:value,site:value)");
-//     println!("    --hash <HASH>           Hash parameters (method:value,cut:value,end:value,upper-start:value)");
-//     println!("    --slkv <SLKV>           String key-value configuration");
-//     println!("    --sslf <SSLF>           Secure string format configuration");
-//     println!("    --file <FILE>           Input file path");
-//     println!("    --save <FILE>           Save file path");
-//     println!("    --show-config           Show configuration information and exit");
-//     println!("    --show-platform         Show platform information and exit");
-//     println!("    --once                  Run once and exit");
-//     println!("    --mode <MODE>           Operation mode [default: interactive]");
-//     println!("    --help, -h              Show help information");
-//     println!("    --version, -v           Show version information");
-//     println!();
-//     println!("EXAMPLES:");
-//     println!("    # Basic usage");
-//     println!("    pass-craft --text \"name:john,site:example.com\" --hash \"method:sha256,cut:10\"");
-//     println!();
-//     println!("    # Read configuration from file");
-//     println!("    pass-craft --file config.txt --save passwords.txt");
-//     println!();
-//     println!("    # Show platform information");
-//     println!("    pass-craft --show-platform");
-//     println!();
-//     println!("    # Show configuration");
-//     println!("    pass-craft --show-config --text \"name:test,site:example.com\"");
-// }
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
-    
-    let platform = PlatformInfo::new();
-    
-    // First parse command line arguments
-    let cli_args = CliArgs::parse();
-    
-    // Check help and version parameters
-    // let args: Vec<String> = std::env::args().collect();
-    // if args.iter().any(|arg| arg == "--help" || arg == "-h") {
-    //     print_help();
-    //     return Ok(());
-    // }
-    
-    // if args.iter().any(|arg| arg == "--version" || arg == "-v") {
-    //     println!("pass-craft v{}", env!("CARGO_PKG_VERSION"));
-    //     return Ok(());
-    // }
-    
-    // Show platform information
-    if cli_args.show_platform {
-        info_step("Platform Information", 50, '=');
-        println!("Operating System: {}", platform.os);
-        println!("Architecture: {}", platform.arch);
-        println!("Family: {}", platform.family);
-        println!("Display Format: {}", platform.display());
         return Ok(());
     }
     
-    info!("🚀 Starting Password Hash Generator on {}", platform.display());
-    
     // Load configuration
+    let mut timings = Timings::default();
+    let started = Instant::now();
     let config = match AppConfig::new() {
         Ok(config) => config,
         Err(e) => {
@@ -732,6 +340,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
+    timings.record("reading", started.elapsed());
     
     // Show configuration information
     if cli_args.show_config {
@@ -739,12 +348,124 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
     
+    // Show which source set each setting
+    if cli_args.trace_provenance {
+        info_step("Configuration Provenance", 50, '=');
+        for (field, value, source) in config.trace_provenance() {
+            info_status(&format!("{} = '{}' (from {})", field, value, source), 3);
+        }
+        return Ok(());
+    }
+    
+    // Show where the password's strength comes from
+    if cli_args.explain_entropy {
+        info_step("Entropy Breakdown", 50, '=');
+        for line in explain_entropy(&config).lines() {
+            info_status(&line, 3);
+        }
+        return Ok(());
+    }
+    
     // Validate configuration
     if let Err(e) = config.validate() {
         info_step("Configuration Validation", 50, '!');
         info_status(&format!("{} - Configuration validation failed: {}", get_time_now(), e), 1);
         std::process::exit(1);
     }
+    for warning in config.warnings() {
+        info_status(&format!("{} - {}", get_time_now(), warning), 2);
+    }
+    if cli_args.random_nonce {
+        if let Some(nonce) = &config.nonce {
+            info_status(&format!("{} - Random nonce: {} (pass --nonce {} to recompute)", get_time_now(), nonce, nonce), 2);
+        }
+    }
+    
+    // Compare every algorithm side by side, never saves
+    if cli_args.compare_algorithms {
+        match compare_algorithms(&config, cli_args.allow_weak) {
+            Ok(rows) => {
+                info_step("Algorithm Comparison", 50, '=');
+                for (method, password) in rows {
+                    println!("{:<10} {}", method, password);
+                }
+            }
+            Err(e) => {
+                info_status(&format!("{} - Comparison failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    
+    // Fail when repeated generation isn't stable
+    if let Some(runs) = cli_args.verify_determinism {
+        match verify_determinism(&config, runs) {
+            Ok(result) => info_status(&format!("{} - {} runs produced the same result: {}", get_time_now(), runs, result), 0),
+            Err(e) => {
+                info_status(&format!("{} - Determinism check failed: {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    
+    // Print the configuration fingerprint only
+    if cli_args.config_hash {
+        println!("{}", config_fingerprint(&config));
+        return Ok(());
+    }
+    
+    // Print the raw digest only
+    if cli_args.print_digest {
+        match raw_digest(&config) {
+            Ok(digest) => println!("{}", digest),
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    
+    // Format a pre-hashed digest only
+    if let Some(hex) = &cli_args.input_hash {
+        match format_input_hash(hex, &config) {
+            Ok(password) => println!("{}", password),
+            Err(e) => {
+                info_status(&format!("{} - {}", get_time_now(), e), 1);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    
+    // Generate several numbered variants
+    if cli_args.count == 0 {
+        info_status(&format!("{} - --count must be at least 1", get_time_now()), 1);
+        std::process::exit(1);
+    }
+    if cli_args.count > 1 || cli_args.count_from.is_some() {
+        info_step("Password Variants", 50, '=');
+        for variant in variant_configs(&config, cli_args.count, cli_args.count_from) {
+            let counter = variant.counter.unwrap_or_default();
+            match generate_password_hash(&variant) {
+                Ok(result) => {
+                    info_status(&format!("{} - Variant {}: {}", get_time_now(), counter, result), 0);
+                    for (save_path, outcome) in save_result(&variant, &result) {
+                        if let Err(e) = outcome {
+                            info_status(&format!("{} - Save to {} failed: {}", get_time_now(), save_path, e), 1);
+                        }
+                    }
+                }
+                Err(e) => {
+                    info_status(&format!("{} - Variant {} failed: {}", get_time_now(), counter, e), 1);
+                    std::process::exit(1);
+                }
+            }
+        }
+        return Ok(());
+    }
     
     // Show current configuration summary
     info_step("Current Configuration", 50, '=');
@@ -756,34 +477,98 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
              config.cut_length, config.end_char, config.upper_start), 0);
     
     // Generate password hash
-    match generate_password_hash(&config) {
+    match generate_password_hash_timed(&config, &mut timings) {
         Ok(result) => {
             info_step("Password Generation Complete", 50, '=');
-            info_status(&format!("{} - Generated Password: {}", get_time_now(), result), 0);
+            if cli_args.output_width > 0 {
+                // Wrapped lines start at column 0 so each holds exactly N characters
+                info_status(&format!("{} - Generated Password:\n{}", get_time_now(), wrap_display(&result, cli_args.output_width)), 0);
+            } else {
+                info_status(&format!("{} - Generated Password: {}", get_time_now(), result), 0);
+            }
+            
+            // Print the digest in extra encodings
+            if let Some(spec) = &cli_args.also_encode {
+                match also_encode(&config, spec) {
+                    Ok(encoded) => {
+                        for (encoding, value) in encoded {
+                            info_status(&format!("Digest ({}): {}", encoding, value), 0);
+                        }
+                    }
+                    Err(e) => {
+                        info_status(&format!("{} - {}", get_time_now(), e), 1);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            
+            // Print a shell assignment for eval
+            if let Some(var) = &cli_args.as_env {
+                match env_assignment(var, &result_get_password(&result, &config)) {
+                    Ok(assignment) => println!("{}", assignment),
+                    Err(e) => {
+                        info_status(&format!("{} - {}", get_time_now(), e), 1);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            
+            // Reject breached passwords
+            if cli_args.check_hibp {
+                let fetch_range = |prefix: &str| {
+                    retry_with_backoff(cli_args.retries, std::time::Duration::from_millis(500), || hibp_fetch_range(prefix))
+                };
+                match hibp_check(&result_get_password(&result, &config), fetch_range) {
+                    Ok(0) => info_status(&format!("{} - Password not found in HIBP breach data", get_time_now()), 0),
+                    Ok(count) => {
+                        info_status(&format!("{} - Password found {} times in HIBP breach data", get_time_now(), count), 1);
+                        std::process::exit(1);
+                    }
+                    Err(e) => info_status(&format!("{} - HIBP check skipped: {}", get_time_now(), e), 2),
+                }
+            }
+            
+            // Emit verifier hash
+            if let Some(spec) = &cli_args.emit_verifier {
+                match compute_verifier(&result_get_password(&result, &config), spec) {
+                    Ok(verifier) => info_status(&format!("{} - Verifier ({}): {}", get_time_now(), spec, verifier), 0),
+                    Err(e) => {
+                        info_status(&format!("{} - Verifier failed: {}", get_time_now(), e), 1);
+                        std::process::exit(1);
+                    }
+                }
+            }
             
             // Save result
-            if let Some(save_path) = &config.output_file {
+            if !config.output_files.is_empty() {
                 info_step("Saving Result", 50, '-');
                 
-                let file_path = &config.input_file;
-                
-                let password_text = if file_path.as_deref() == Some(save_path) {
-                    html_comment_wrap(&result)
-                } else {
-                    let result_wrapped = html_comment_wrap(&result);
-                    format!("{}\n{}", 
-                           config.input_file.as_ref()
-                               .and_then(|path| fs::read_to_string(path).ok())
-                               .unwrap_or_default(),
-                           result_wrapped)
-                };
-
-                match add_password_to_file(save_path, &password_text) {
-                    Ok(()) => {
-                        info_status(&format!("{} - Successfully saved to: {}", get_time_now(), save_path), 0);
+                // Catch configs that silently changed a stored password
+                if cli_args.diff_against_saved {
+                    match diff_against_saved(&config, &result_get_password(&result, &config), cli_args.yes) {
+                        Ok(messages) => {
+                            for message in messages {
+                                info_status(&format!("{} - {}", get_time_now(), message), 3);
+                            }
+                        }
+                        Err(e) => {
+                            info_status(&format!("{} - {}", get_time_now(), e), 1);
+                            std::process::exit(1);
+                        }
                     }
-                    Err(e) => {
-                        info_status(&format!("{} - Save failed: {}", get_time_now(), e), 1);
+                }
+                
+                let started = Instant::now();
+                let outcomes = save_result(&config, &result);
+                timings.record("saving", started.elapsed());
+                for (save_path, outcome) in outcomes {
+                    match outcome {
+                        Ok(()) => {
+                            info_status(&format!("{} - Successfully saved to: {}", get_time_now(), save_path), 0);
+                        }
+                        Err(e) => {
+                            info_status(&format!("{} - Save to {} failed: {}", get_time_now(), save_path, e), 1);
+                        }
                     }
                 }
             }
@@ -801,130 +586,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
     
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_platform_info() {
-        let platform = PlatformInfo::new();
-        
-        // Verify platform information is not empty
-        assert!(!platform.os.is_empty());
-        assert!(!platform.arch.is_empty());
-        assert!(!platform.family.is_empty());
-        
-        // Verify display format
-        let display = platform.display();
-        assert!(display.contains(&platform.os));
-        assert!(display.contains(&platform.arch));
-    }
-
-    #[test]
-    fn test_info_step_alignment() {
-        // Test various message lengths
-        info_step("Configuration", 50, '=');
-        info_step("Generating Password Hash", 50, '-');
-        info_step("Test", 20, '*');
-        info_step("A", 10, '-');
-        
-        // Test long messages (should display directly)
-        info_step("This is a very long message that exceeds the specified length", 30, '+');
-    }
-
-    #[test]
-    fn test_info_status() {
-        // Test all status types
-        info_status("Success message", 0);
-        info_status("Error message", 1);
-        info_status("Warning message", 2);
-        info_status("Info message", 3);
-        info_status("Default info message", 99);
-    }
-
-    #[test]
-    fn test_get_time_now() {
-        let time1 = get_time_now();
-        let time2 = get_time_now();
-        
-        // Verify time format
-        assert_eq!(time1.len(), 19); // "YYYY-MM-DD HH:MM:SS"
-        assert!(time1.contains('-')); // Contains date separator
-        assert!(time1.contains(':')); // Contains time separator
-        
-        // Two calls should get different times (or at least same format)
-        assert_eq!(time1.len(), time2.len());
-    }
-
-    #[test]
-    fn test_config_validation() {
-        let valid_config = AppConfig {
-            method: "SHA256".to_string(),
-            cut_length: 8,
-            end_char: "!".to_string(),
-            upper_start: 3,
-            name: "test".to_string(),
-            email: "test@example.com".to_string(),
-            site: "example.com".to_string(),
-            input_file: None,
-            output_file: None,
-            platform_identifier: "test".to_string(),
-        };
-        
-        assert!(valid_config.validate().is_ok());
-        
-        // Test invalid configurations
-        let invalid_configs = vec![
-            AppConfig { method: "INVALID".to_string(), ..valid_config.clone() }, // Invalid algorithm
-            AppConfig { cut_length: 0, ..valid_config.clone() }, // Cut length too small
-            AppConfig { cut_length: 65, ..valid_config.clone() }, // Cut length too large
-            AppConfig { upper_start: 10, ..valid_config.clone() }, // Upper start exceeds cut length
-        ];
-        
-        for (i, config) in invalid_configs.iter().enumerate() {
-            assert!(config.validate().is_err(), "Test case {} should fail", i);
+    // Show phase timings
+    if cli_args.timings {
+        if cli_args.json_output {
+            println!("{}", timings.to_json());
+        } else {
+            info_step("Timings", 50, '-');
+            for line in timings.report() {
+                info_status(&line, 3);
+            }
         }
     }
-
-    #[test]
-    fn test_generate_password_hash() {
-        let config = AppConfig {
-            method: "MD5".to_string(), // Use MD5 for easier testing
-            cut_length: 6,
-            end_char: "!".to_string(),
-            upper_start: 2,
-            name: "test".to_string(),
-            email: "test@example.com".to_string(),
-            site: "example.com".to_string(),
-            ..Default::default()
-        };
-        
-        let result = generate_password_hash(&config).unwrap();
-        assert!(result.starts_with("test,"));
-        assert!(result.ends_with(",example.com"));
-        assert!(result.contains("!"));
-    }
-
-    #[test]
-    fn test_hash_functions() {
-        // Test various hash algorithms
-        let test_string = "hello world";
-        
-        let md5_hash = get_string_hash(test_string, "MD5");
-        let sha1_hash = get_string_hash(test_string, "SHA1");
-        let sha256_hash = get_string_hash(test_string, "SHA256");
-        let sha512_hash = get_string_hash(test_string, "SHA512");
-        
-        // Verify hash lengths
-        assert_eq!(md5_hash.len(), 32);
-        assert_eq!(sha1_hash.len(), 40);
-        assert_eq!(sha256_hash.len(), 64);
-        assert_eq!(sha512_hash.len(), 128);
-        
-        // Verify known hash values
-        assert_eq!(md5_hash, "5eb63bbbe01eeed093cb22bb8f5acdc3");
-    }
-}
\ No newline at end of file
+    
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Golden vectors for password generation
+//!
+//! Each vector pins the output of the public API for a fixed identity, so any
+//! refactor that silently changes a generated password fails here.
+
+// Verify-only builds have no generation to pin
+#![cfg(not(feature = "verify-only"))]
+
+use pass_craft::{generate_password_hash, AppConfig};
+
+// (method, hash parameters, expected result)
+const GOLDEN_VECTORS: &[(&str, &str, &str)] = &[
+    ("MD5", "", "john,9133550!,john.com"),
+    ("MD5", "cut:12", "john,9133550941e!,john.com"),
+    ("MD5", "cut:10,end:+,upper-start:5", "john,913355094+,john.com"),
+    ("MD5", "end:,upper-start:0", "john,91335509,john.com"),
+    ("MD5", "encoding:base64,cut:12", "john,KTNVCUHrQlb!,john.com"),
+    ("MD5", "encoding:base64,cut:9,end:#", "john,KTNVCUHr#,john.com"),
+    ("SHA1", "", "john,725433b!,john.com"),
+    ("SHA1", "cut:12", "john,725433b471f!,john.com"),
+    ("SHA1", "cut:10,end:+,upper-start:5", "john,725433b47+,john.com"),
+    ("SHA1", "end:,upper-start:0", "john,725433b4,john.com"),
+    ("SHA1", "encoding:base64,cut:12", "john,CLQztHH0SJn!,john.com"),
+    ("SHA1", "encoding:base64,cut:9,end:#", "john,CLQztHH0#,john.com"),
+    ("SHA256", "", "john,6D39a81!,john.com"),
+    ("SHA256", "cut:12", "john,6D39a810723!,john.com"),
+    ("SHA256", "cut:10,end:+,upper-start:5", "john,6D39A8107+,john.com"),
+    ("SHA256", "end:,upper-start:0", "john,6d39a810,john.com"),
+    ("SHA256", "encoding:base64,cut:12", "john,BTMoEHI8VmW!,john.com"),
+    ("SHA256", "encoding:base64,cut:9,end:#", "john,BTMoEHI8#,john.com"),
+    ("SHA512", "", "john,B5Cb304!,john.com"),
+    ("SHA512", "cut:12", "john,B5Cb3043cd6!,john.com"),
+    ("SHA512", "cut:10,end:+,upper-start:5", "john,B5CB3043c+,john.com"),
+    ("SHA512", "end:,upper-start:0", "john,b5cb3043,john.com"),
+    ("SHA512", "encoding:base64,cut:12", "john,TCSwQ81rdW5!,john.com"),
+    ("SHA512", "encoding:base64,cut:9,end:#", "john,TCSwQ81r#,john.com"),
+    ("SHA512", "cut:8,end:+,upper-start:5", "john,B5CB304+,john.com"),
+];
+
+fn golden_config(method: &str, hash: &str) -> AppConfig {
+    let mut config = AppConfig {
+        name: "john".to_string(),
+        email: "john@gmail.com".to_string(),
+        site: "john.com".to_string(),
+        method: method.to_string(),
+        ..Default::default()
+    };
+    AppConfig::apply_hash_config(&mut config, hash).unwrap();
+    config
+}
+
+#[test]
+fn test_golden_vectors() {
+    for (method, hash, expected) in GOLDEN_VECTORS {
+        let config = golden_config(method, hash);
+        assert!(config.validate().is_ok(), "{} '{}' should be valid", method, hash);
+        
+        let result = generate_password_hash(&config).unwrap();
+        assert_eq!(&result, expected, "golden vector mismatch for {} '{}'", method, hash);
+    }
+}
+
+#[test]
+fn test_golden_byte_truncation() {
+    let config = AppConfig {
+        truncate_unit: "bytes".to_string(),
+        ..golden_config("SHA256", "cut:6")
+    };
+    assert_eq!(generate_password_hash(&config).unwrap(), "john,6D39a810723!,john.com");
+}
+
+#[test]
+fn test_golden_counter() {
+    let config = AppConfig {
+        counter: Some(3),
+        ..golden_config("SHA256", "")
+    };
+    assert_eq!(generate_password_hash(&config).unwrap(), "john,EEDc92e!,john.com");
+}
@@ -0,0 +1,25 @@
+//! Stable, sorted output of `--list-algorithms`
+
+use std::process::Command;
+
+fn list_algorithms() -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_pass-craft"))
+        .arg("--list-algorithms")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn list_algorithms_is_stable_and_sorted() {
+    let first = list_algorithms();
+    assert_eq!(first, list_algorithms());
+    
+    let stdout = String::from_utf8(first).unwrap();
+    let names: Vec<&str> = stdout.lines().map(|line| line.split_whitespace().next().unwrap()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    assert!(names.contains(&"SHA512"));
+}
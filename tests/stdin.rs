@@ -0,0 +1,28 @@
+//! Reading the config from stdin with `--file -`
+
+// Verify-only builds stop before reading a config
+#![cfg(not(feature = "verify-only"))]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use pass_craft::{parse_sslf, raw_digest};
+
+#[test]
+fn config_from_stdin() {
+    let line = "name:john,email:john@gmail.com,site:john.com;method:sha256,cut:12";
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pass-craft"))
+        .args(["--file", "-", "--print-digest"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap()
+        .write_all(format!("# piped config\n{}\n", line).as_bytes())
+        .unwrap();
+    
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().last(), Some(raw_digest(&parse_sslf(line).unwrap()).unwrap().as_str()));
+}
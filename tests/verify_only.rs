@@ -0,0 +1,35 @@
+//! Commands compiled out of verify-only builds are rejected
+
+// Default builds run these commands for real
+#![cfg(feature = "verify-only")]
+
+use pass_craft::GENERATION_DISABLED;
+use std::process::Command;
+
+#[test]
+fn verify_only_rejects_maintenance_commands() {
+    let commands: &[&[&str]] = &[
+        &["migrate", "--from", "old.txt", "--to", "new.toml"],
+        &["--init-config"],
+        &["prune-archived", "saved.txt", "--older-than", "30"],
+        &["--hash-file", "Cargo.toml"],
+        &["check-clock", "saved.txt"],
+        &["--explain-diff", "--a", "name:john", "--b", "name:jane"],
+    ];
+    for args in commands {
+        let output = Command::new(env!("CARGO_BIN_EXE_pass-craft"))
+            .args(*args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{:?} should be rejected", args);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(GENERATION_DISABLED), "{:?}: {}", args, stdout);
+    }
+    
+    // Verification commands still work
+    let output = Command::new(env!("CARGO_BIN_EXE_pass-craft"))
+        .arg("--list-algorithms")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}